@@ -0,0 +1,56 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ratatui::{buffer::Buffer, layout::Rect, widgets::StatefulWidget};
+use ratatui_input::{Input, InputState, Message};
+
+/// Length of the value in characters, long enough for the char index scans to show up
+const VALUE_LEN: usize = 50_000;
+
+fn long_state() -> InputState {
+    let mut state = InputState::default();
+    state.set_text("žđščć🎈ab".repeat(VALUE_LEN / 8));
+    state
+}
+
+fn insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+
+    // Every insert is undone right away, so the value keeps its length between iterations
+    group.bench_function("at_start", |b| {
+        let mut state = long_state();
+        state.handle_message(Message::JumpToStart);
+        b.iter(|| {
+            state.handle_message(Message::Char('a'));
+            state.handle_message(Message::DeleteBeforeCursor);
+        });
+    });
+
+    group.bench_function("at_end", |b| {
+        let mut state = long_state();
+        b.iter(|| {
+            state.handle_message(Message::Char('a'));
+            state.handle_message(Message::DeleteBeforeCursor);
+        });
+    });
+
+    group.finish();
+}
+
+fn render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+
+    for width in [10, 80, 200] {
+        let area = Rect::new(0, 0, width, 1);
+        let mut buf = Buffer::empty(area);
+        let mut state = long_state();
+        let widget = Input::default();
+
+        group.bench_with_input(BenchmarkId::from_parameter(width), &width, |b, _| {
+            b.iter(|| widget.clone().render(area, &mut buf, &mut state));
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, insert, render);
+criterion_main!(benches);
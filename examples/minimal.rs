@@ -18,8 +18,7 @@ fn main() -> Result<(), std::io::Error> {
     loop {
         terminal.draw(|frame| {
             let area = frame.size();
-            let input = Input::default();
-            frame.render_stateful_widget(input, area, &mut state);
+            Input::default().render_with_cursor(frame, area, &mut state);
         })?;
         if event::poll(std::time::Duration::from_millis(16))? {
             let event = event::read()?;
@@ -27,7 +26,7 @@ fn main() -> Result<(), std::io::Error> {
                 if key.code == KeyCode::Esc {
                     break;
                 } else {
-                    state.handle_message(event.into());
+                    state.handle_event(event);
                 }
             }
         }
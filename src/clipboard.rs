@@ -0,0 +1,36 @@
+/// Clipboard used by the [`crate::InputState`] for [`crate::Message::Copy`], [`crate::Message::Cut`]
+/// and [`crate::Message::PasteFromClipboard`], e.g. one backed by the browser when running in xterm.js
+pub trait Clipboard {
+    /// Contents of the clipboard, [`None`] if it is empty or can't be read
+    fn get(&mut self) -> Option<String>;
+    /// Replace the contents of the clipboard
+    fn set(&mut self, text: String);
+}
+
+/// The system clipboard, used by default with the `clipboard` feature. Errors are ignored, as if the clipboard was empty
+#[cfg(feature = "clipboard")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SystemClipboard;
+
+#[cfg(feature = "clipboard")]
+impl Clipboard for SystemClipboard {
+    fn get(&mut self) -> Option<String> {
+        use ::clipboard::ClipboardProvider;
+        ::clipboard::ClipboardContext::new()
+            .and_then(|mut cc| cc.get_contents())
+            .ok()
+    }
+
+    fn set(&mut self, text: String) {
+        use ::clipboard::ClipboardProvider;
+        let _ = ::clipboard::ClipboardContext::new().and_then(|mut cc| cc.set_contents(text));
+    }
+}
+
+/// Clipboard an [`crate::InputState`] starts with, the system one if there is one
+pub(crate) fn default_clipboard() -> Option<Box<dyn Clipboard>> {
+    #[cfg(feature = "clipboard")]
+    return Some(Box::new(SystemClipboard));
+    #[cfg(not(feature = "clipboard"))]
+    return None;
+}
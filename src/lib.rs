@@ -1,5 +1,11 @@
 //! Simple to use Input widget for use with the ratatui crate
 //! See the exmaples for more info on using it
+//!
+//! Conversions from crossterm events into [`Message`]s are behind the `crossterm` feature, which is enabled by default.
+//! Termion keys can be converted the same way with the `termion` feature.
+//! The system clipboard is used through the `clipboard` feature, also enabled by default.
+//! It isn't available in the browser, so WebAssembly builds set `default-features = false` and give any other
+//! [`Clipboard`] to [`InputState::set_clipboard`].
 
 #![deny(
     bad_style,
@@ -26,10 +32,18 @@
     unused_results
 )]
 
+mod clipboard;
 mod message;
+mod normalization;
 mod state;
+mod vim;
 mod widget;
+mod word;
 
+pub use clipboard::*;
 pub use message::*;
+pub use normalization::*;
 pub use state::*;
+pub use vim::*;
 pub use widget::*;
+pub use word::*;
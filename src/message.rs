@@ -1,8 +1,8 @@
-use clipboard::ClipboardProvider;
+#[cfg(feature = "crossterm")]
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 
 /// Messages passed to the InputState
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum Message {
     /// Empty message, no state change happens. Used to avoid the use of [`Option<Message>`].
     Empty,
@@ -14,6 +14,12 @@ pub enum Message {
     DeleteOnCursor,
     /// Delete the character before the cursor
     DeleteBeforeCursor,
+    /// Delete the word before the cursor, together with any non-word characters between it and the cursor
+    DeleteWordBeforeCursor,
+    /// Delete from the cursor up to and including the next occurrence of the character, like vi `df`
+    DeleteForwardTo(char),
+    /// Delete from the previous occurrence of the character up to the cursor, like vi `dF`
+    DeleteBackTo(char),
     /// Move the cursor to the left
     MoveLeft,
     /// Move the cursor to the left and start/continou text selection
@@ -22,6 +28,14 @@ pub enum Message {
     MoveRight,
     /// Move the cursor to the right and start/continue the text selection
     MoveRightWithSelection,
+    /// Extend the selection to the start of the previous word, keeping where the selection started
+    SelectWordLeft,
+    /// Extend the selection to the end of the next word, keeping where the selection started
+    SelectWordRight,
+    /// Move the cursor to the character in the given column of the visible text and end the selection, e.g. on a mouse click
+    SetCursorAtColumn(usize),
+    /// Move the cursor to the given character index, selecting everything from where the cursor or selection started
+    SelectToIndex(usize),
     /// Jump the cursor to the end
     JumpToEnd,
     /// Jump the cursor to the end and seect everything in between the end and start position
@@ -34,18 +48,35 @@ pub enum Message {
     Char(char),
     /// Insert a string at the current cursor position. If we have a selection, the selection will get replaced
     Paste(String),
+    /// Type a whole string at the cursor as a single edit, e.g. a grapheme cluster made of several characters.
+    /// Like [`Message::Char`] it goes right at the cursor and respects the insert mode, newlines are dropped unless multiline
+    Insert(String),
+    /// Overwrite the character under the cursor without moving the cursor, like vi's `r`. Does nothing past the end
+    ReplaceChar(char),
+    /// Paste the clipboard contents like [`Message::Paste`]. The clipboard is only read once the message is handled
+    PasteFromClipboard,
     /// Toggle the insert mode
     ToggleInsertMode,
+    /// Toggle showing a masked value as it is, e.g. to peek at a typed password
+    ToggleReveal,
     /// Copy selected text or if there is no selection, the entire input value and add it to the clipboard
     Copy,
     /// Cut selected text or if there is no selection the entire input and add it to the clipboard
     Cut,
-    //TODO: SelectAll
+    /// Select the entire value
+    SelectAll,
+    /// End the selection, keeping the cursor and value as they are, e.g. when clicking elsewhere
+    ClearSelection,
+    /// Replace the word before the cursor with the next completion candidate, see [`crate::InputState::set_candidates`]
+    CompleteNext,
+    /// Replace the word before the cursor with the previous completion candidate
+    CompletePrev,
     //TODO: SelectWord
     //TODO: JumpToEndOfWord
     //TODO: JumpToStartOfWord
 }
 
+#[cfg(feature = "crossterm")]
 impl From<Event> for Message {
     fn from(value: Event) -> Self {
         match value {
@@ -59,23 +90,40 @@ impl From<Event> for Message {
     }
 }
 
+#[cfg(feature = "crossterm")]
 impl From<KeyEvent> for Message {
     fn from(value: KeyEvent) -> Self {
         if value.kind == KeyEventKind::Release {
             Message::Empty
         } else {
             match value.code {
-                KeyCode::Backspace => Message::DeleteBeforeCursor,
-                KeyCode::Enter => Message::RemoveFocus,
-                KeyCode::Left => {
+                KeyCode::Backspace => {
+                    if value.modifiers == KeyModifiers::ALT {
+                        Message::DeleteWordBeforeCursor
+                    } else {
+                        Message::DeleteBeforeCursor
+                    }
+                }
+                KeyCode::Enter => {
                     if value.modifiers == KeyModifiers::SHIFT {
+                        Message::Char('\n')
+                    } else {
+                        Message::RemoveFocus
+                    }
+                }
+                KeyCode::Left => {
+                    if value.modifiers == KeyModifiers::CONTROL | KeyModifiers::SHIFT {
+                        Message::SelectWordLeft
+                    } else if value.modifiers == KeyModifiers::SHIFT {
                         Message::MoveLeftWithSelection
                     } else {
                         Message::MoveLeft
                     }
                 }
                 KeyCode::Right => {
-                    if value.modifiers == KeyModifiers::SHIFT {
+                    if value.modifiers == KeyModifiers::CONTROL | KeyModifiers::SHIFT {
+                        Message::SelectWordRight
+                    } else if value.modifiers == KeyModifiers::SHIFT {
                         Message::MoveRightWithSelection
                     } else {
                         Message::MoveRight
@@ -101,10 +149,29 @@ impl From<KeyEvent> for Message {
                 KeyCode::PageDown => Message::Empty,
                 KeyCode::Tab => Message::Char('\t'),
                 KeyCode::BackTab => Message::Empty,
-                KeyCode::Delete => Message::DeleteOnCursor,
-                KeyCode::Insert => Message::ToggleInsertMode,
+                KeyCode::Delete => {
+                    if value.modifiers == KeyModifiers::SHIFT {
+                        Message::Cut
+                    } else {
+                        Message::DeleteOnCursor
+                    }
+                }
+                KeyCode::Insert => {
+                    if value.modifiers == KeyModifiers::SHIFT {
+                        Message::PasteFromClipboard
+                    } else {
+                        Message::ToggleInsertMode
+                    }
+                }
                 KeyCode::F(_) => Message::Empty,
                 KeyCode::Char(c) => match c {
+                    'a' => {
+                        if value.modifiers == KeyModifiers::CONTROL {
+                            Message::SelectAll
+                        } else {
+                            Message::Char('a')
+                        }
+                    }
                     'c' => {
                         if value.modifiers == KeyModifiers::CONTROL {
                             Message::Copy
@@ -121,16 +188,18 @@ impl From<KeyEvent> for Message {
                     }
                     'v' => {
                         if value.modifiers == KeyModifiers::CONTROL {
-                            match clipboard::ClipboardContext::new()
-                                .and_then(|mut cc| cc.get_contents())
-                            {
-                                Ok(str) => Message::Paste(str),
-                                Err(_) => Message::Empty,
-                            }
+                            Message::PasteFromClipboard
                         } else {
                             Message::Char('v')
                         }
                     }
+                    'r' => {
+                        if value.modifiers == KeyModifiers::CONTROL {
+                            Message::ToggleReveal
+                        } else {
+                            Message::Char('r')
+                        }
+                    }
                     c => Message::Char(c),
                 },
                 KeyCode::Null => Message::Empty,
@@ -148,3 +217,150 @@ impl From<KeyEvent> for Message {
         }
     }
 }
+
+#[cfg(feature = "termion")]
+impl From<termion::event::Key> for Message {
+    fn from(value: termion::event::Key) -> Self {
+        use termion::event::Key;
+
+        match value {
+            Key::Backspace => Message::DeleteBeforeCursor,
+            Key::Delete => Message::DeleteOnCursor,
+            Key::Left => Message::MoveLeft,
+            Key::Right => Message::MoveRight,
+            Key::Home => Message::JumpToStart,
+            Key::End => Message::JumpToEnd,
+            Key::Insert => Message::ToggleInsertMode,
+            Key::Esc => Message::RemoveFocus,
+            Key::Char('\n') => Message::RemoveFocus,
+            Key::Char(c) => Message::Char(c),
+            Key::Ctrl('a') => Message::SelectAll,
+            Key::Ctrl('c') => Message::Copy,
+            Key::Ctrl('x') => Message::Cut,
+            Key::Ctrl('v') => Message::PasteFromClipboard,
+            Key::Ctrl('r') => Message::ToggleReveal,
+            _ => Message::Empty,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "crossterm"))]
+mod crossterm_tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::*;
+
+    #[test]
+    fn enter_submits() {
+        let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(Message::from(key), Message::RemoveFocus);
+    }
+
+    #[test]
+    fn shift_enter_inserts_newline() {
+        let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::SHIFT);
+        assert_eq!(Message::from(key), Message::Char('\n'));
+    }
+
+    #[test]
+    fn ctrl_shift_arrows_select_words() {
+        let key = KeyEvent::new(KeyCode::Left, KeyModifiers::CONTROL | KeyModifiers::SHIFT);
+        assert_eq!(Message::from(key), Message::SelectWordLeft);
+
+        let key = KeyEvent::new(KeyCode::Right, KeyModifiers::CONTROL | KeyModifiers::SHIFT);
+        assert_eq!(Message::from(key), Message::SelectWordRight);
+
+        let key = KeyEvent::new(KeyCode::Right, KeyModifiers::SHIFT);
+        assert_eq!(Message::from(key), Message::MoveRightWithSelection);
+    }
+
+    #[test]
+    fn alt_backspace_deletes_word() {
+        let key = KeyEvent::new(KeyCode::Backspace, KeyModifiers::ALT);
+        assert_eq!(Message::from(key), Message::DeleteWordBeforeCursor);
+
+        let key = KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(Message::from(key), Message::DeleteBeforeCursor);
+    }
+
+    #[test]
+    fn ctrl_a_selects_all() {
+        let key = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL);
+        assert_eq!(Message::from(key), Message::SelectAll);
+
+        let key = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE);
+        assert_eq!(Message::from(key), Message::Char('a'));
+    }
+
+    #[test]
+    fn shift_delete_cuts() {
+        let key = KeyEvent::new(KeyCode::Delete, KeyModifiers::SHIFT);
+        assert_eq!(Message::from(key), Message::Cut);
+
+        let key = KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE);
+        assert_eq!(Message::from(key), Message::DeleteOnCursor);
+    }
+
+    #[test]
+    fn ctrl_v_does_not_read_clipboard() {
+        // Only the marker is produced, the clipboard is read when the message is handled
+        let key = KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL);
+        assert_eq!(Message::from(key), Message::PasteFromClipboard);
+
+        let key = KeyEvent::new(KeyCode::Char('v'), KeyModifiers::NONE);
+        assert_eq!(Message::from(key), Message::Char('v'));
+    }
+
+    #[test]
+    fn ctrl_r_toggles_reveal() {
+        let key = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        assert_eq!(Message::from(key), Message::ToggleReveal);
+
+        let key = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE);
+        assert_eq!(Message::from(key), Message::Char('r'));
+    }
+
+    #[test]
+    fn shift_insert_pastes() {
+        let key = KeyEvent::new(KeyCode::Insert, KeyModifiers::SHIFT);
+        assert_eq!(Message::from(key), Message::PasteFromClipboard);
+
+        let key = KeyEvent::new(KeyCode::Insert, KeyModifiers::NONE);
+        assert_eq!(Message::from(key), Message::ToggleInsertMode);
+    }
+}
+
+#[cfg(all(test, feature = "termion"))]
+mod termion_tests {
+    use termion::event::Key;
+
+    use super::*;
+
+    #[test]
+    fn movement_keys() {
+        assert_eq!(Message::from(Key::Left), Message::MoveLeft);
+        assert_eq!(Message::from(Key::Right), Message::MoveRight);
+        assert_eq!(Message::from(Key::Home), Message::JumpToStart);
+        assert_eq!(Message::from(Key::End), Message::JumpToEnd);
+    }
+
+    #[test]
+    fn editing_keys() {
+        assert_eq!(Message::from(Key::Backspace), Message::DeleteBeforeCursor);
+        assert_eq!(Message::from(Key::Delete), Message::DeleteOnCursor);
+        assert_eq!(Message::from(Key::Insert), Message::ToggleInsertMode);
+        assert_eq!(Message::from(Key::Ctrl('a')), Message::SelectAll);
+        assert_eq!(Message::from(Key::Ctrl('c')), Message::Copy);
+        assert_eq!(Message::from(Key::Ctrl('x')), Message::Cut);
+        assert_eq!(Message::from(Key::Ctrl('v')), Message::PasteFromClipboard);
+        assert_eq!(Message::from(Key::Ctrl('r')), Message::ToggleReveal);
+    }
+
+    #[test]
+    fn printable_chars() {
+        assert_eq!(Message::from(Key::Char('ž')), Message::Char('ž'));
+        assert_eq!(Message::from(Key::Char('🎈')), Message::Char('🎈'));
+        assert_eq!(Message::from(Key::Char('\n')), Message::RemoveFocus);
+        assert_eq!(Message::from(Key::Esc), Message::RemoveFocus);
+    }
+}
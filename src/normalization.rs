@@ -0,0 +1,20 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// Unicode normalization form of the text typed or pasted into the [`crate::InputState`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Canonical composition, e.g. `e` followed by a combining acute accent is stored as `é`
+    Nfc,
+    /// Canonical decomposition, e.g. `é` is stored as `e` followed by a combining acute accent
+    Nfd,
+}
+
+impl Normalization {
+    /// Convert the text into this normalization form
+    pub fn normalize(&self, text: &str) -> String {
+        match self {
+            Normalization::Nfc => text.nfc().collect(),
+            Normalization::Nfd => text.nfd().collect(),
+        }
+    }
+}
@@ -1,11 +1,20 @@
 use std::{
     cmp::{max, min},
+    fmt::{self, Debug},
     ops::{Deref, Range},
+    time::{Duration, Instant},
 };
 
-use clipboard::ClipboardProvider;
+#[cfg(feature = "crossterm")]
+use crossterm::event::{
+    Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::layout::Rect;
+
+use crate::{clipboard::default_clipboard, Clipboard, Message, Normalization, WordBoundary};
 
-use crate::Message;
+/// Callback called with the new value and its summary after it changes
+pub type OnChangeFn = dyn FnMut(&str, InputInfo);
 
 /// Stored state of the input widget. Used for the cursor position, text selection and windowing/scrolling
 #[derive(Debug, PartialEq, Eq)]
@@ -13,9 +22,41 @@ pub struct InputState {
     value: String,
     cursor_char_idx: usize,
     in_focus: bool,
+    modified: bool,
     insert_mode: bool,
+    revealed: bool,
+    auto_pairs: bool,
+    multiline: bool,
+    reject_control_chars: bool,
+    collapse_whitespace_on_paste: bool,
+    scroll_animation_step: usize,
+    follow_tail: bool,
+    select_all_on_focus: bool,
+    collapse_selection_on_jump: bool,
+    collapse_selection_to_edge: bool,
+    word_boundary: WordBoundary,
+    normalization: Option<Normalization>,
+    max_len: Option<usize>,
     selection_start_char_idx: Option<usize>,
+    highlights: Vec<Range<usize>>,
+    preedit: Option<String>,
+    on_blur: Hook<dyn FnMut(&mut String)>,
+    on_change: Hook<OnChangeFn>,
+    change_debounce: Option<Duration>,
+    change_pending: bool,
+    change_seen_at: Option<Instant>,
+    match_prefix: String,
+    on_match: Hook<dyn FnMut()>,
+    echo: Hook<dyn FnMut(char)>,
+    clipboard: Hook<dyn Clipboard>,
+    copy_fallback: CopyFallback,
+    masked_copy: MaskedCopy,
+    candidates: Vec<String>,
+    completion: Option<Completion>,
     pub(crate) view_window: ViewWindow,
+    pub(crate) min_view_width: usize,
+    /// Cells a tab took up when the input was last rendered
+    pub(crate) tab_width: usize,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -30,6 +71,37 @@ impl ViewWindow {
     pub fn contains(&self, idx: usize) -> bool {
         self.offsett <= idx && self.offsett + self.width > idx
     }
+
+    /// Change the width, keeping the cursor in view
+    pub(crate) fn resize(&mut self, width: usize, cursor_char_idx: usize) {
+        let old_width = self.width;
+        self.width = width;
+
+        if self.width > old_width {
+            // Increase view window width to the left, the remaining increase is added to the right
+            let left_increase = if self.offsett < self.width - old_width {
+                self.offsett
+            } else {
+                self.width - old_width
+            };
+
+            self.offsett -= left_increase;
+        };
+
+        if self.width < old_width {
+            // Shrink view window, so that the cursor falls to the very right of the view window,
+            // the remaining width is then shruk from the left
+            // The cursor can be anywhere in a window that was never rendered, so none of it may underflow
+            let shrink = old_width - self.width;
+            let right_shrink = min(
+                (self.offsett + old_width).saturating_sub(cursor_char_idx + 1),
+                shrink,
+            );
+            let left_shirnk = shrink - right_shrink;
+
+            self.offsett += left_shirnk;
+        };
+    }
 }
 
 impl From<Range<usize>> for ViewWindow {
@@ -47,29 +119,257 @@ impl From<ViewWindow> for Range<usize> {
     }
 }
 
+/// Completion in progress, cycling through the candidates in place of the word typed before it
+#[derive(Debug, PartialEq, Eq)]
+struct Completion {
+    /// Char index where the completed word starts
+    start: usize,
+    /// Word as it was typed, shown again after the last candidate
+    prefix: String,
+    /// Candidate currently in place of the word, [`None`] for the prefix
+    index: Option<usize>,
+}
+
+/// Optional callback stored on the [`InputState`]
+pub(crate) struct Hook<F: ?Sized>(Option<Box<F>>);
+
+impl<F: ?Sized> Default for Hook<F> {
+    fn default() -> Self {
+        Hook(None)
+    }
+}
+
+impl<F: ?Sized> Debug for Hook<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("Some(..)"),
+            None => f.write_str("None"),
+        }
+    }
+}
+
+/// Callbacks cannot be compared, so hooks are equal when both or neither are set
+impl<F: ?Sized> PartialEq for Hook<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.is_some() == other.0.is_some()
+    }
+}
+
+impl<F: ?Sized> Eq for Hook<F> {}
+
 impl Default for InputState {
     fn default() -> Self {
         Self {
             value: String::new(),
             cursor_char_idx: 0,
             in_focus: false,
+            modified: false,
             insert_mode: false,
+            revealed: false,
+            auto_pairs: false,
+            multiline: false,
+            reject_control_chars: false,
+            collapse_whitespace_on_paste: false,
+            scroll_animation_step: 1,
+            follow_tail: false,
+            select_all_on_focus: false,
+            collapse_selection_on_jump: true,
+            collapse_selection_to_edge: false,
+            word_boundary: WordBoundary::default(),
+            normalization: None,
+            max_len: None,
             selection_start_char_idx: None,
+            highlights: Vec::new(),
+            preedit: None,
+            on_blur: Hook::default(),
+            on_change: Hook::default(),
+            change_debounce: None,
+            change_pending: false,
+            change_seen_at: None,
+            match_prefix: String::new(),
+            on_match: Hook::default(),
+            echo: Hook::default(),
+            clipboard: Hook(default_clipboard()),
+            copy_fallback: CopyFallback::default(),
+            masked_copy: MaskedCopy::default(),
+            candidates: Vec::new(),
+            completion: None,
             view_window: ViewWindow {
                 width: 1,
                 offsett: 0,
             },
+            min_view_width: 0,
+            tab_width: 4,
         }
     }
 }
 
 impl InputState {
+    /// State for a password, to be rendered with [`crate::Input::password`]. Copying leaves the clipboard as it is
+    pub fn password() -> Self {
+        Self {
+            masked_copy: MaskedCopy::Nothing,
+            ..Default::default()
+        }
+    }
+
     /// Update the [`InputState`] with the given message
     pub fn handle_message(&mut self, msg: Message) {
+        let _ = self.apply_message(msg, false);
+    }
+
+    /// Handle the message, returning whether the value changed.
+    /// Without `report_change` the value is only compared when needed otherwise, and [`false`] is returned if it isn't
+    fn apply_message(&mut self, msg: Message, report_change: bool) -> bool {
+        // Reading the clipboard is the only IO, it happens here so the key conversions stay pure
+        let msg = match msg {
+            Message::PasteFromClipboard => match self.clipboard_contents() {
+                Some(str) => Message::Paste(str),
+                None => Message::Empty,
+            },
+            msg => msg,
+        };
+        // Any other message ends the completion, the next one starts over from the word before the cursor
+        if !matches!(msg, Message::CompleteNext | Message::CompletePrev) {
+            self.completion = None;
+        }
+        let inserts_text = matches!(
+            msg,
+            Message::Char(_) | Message::Insert(_) | Message::Paste(_) | Message::ReplaceChar(_)
+        );
+        let typed_char = match msg {
+            Message::Char(c) => Some(c),
+            _ => None,
+        };
+        // Only keep the old value around when the message can change it and somebody wants to know,
+        // the modified flag only until it is set
+        let old_value = (may_edit(&msg)
+            && (report_change
+                || !self.modified
+                || self.on_change.0.is_some()
+                || self.on_match.0.is_some()
+                || self.echo.0.is_some()))
+        .then(|| self.value.clone());
+        // The tail is only followed while it is in view, scrolling away from it stops the following
+        let follows_tail = self.follow_tail
+            && inserts_text
+            && self.view_window.offsett + self.view_window.width > self.value.chars().count();
+        // Typed text might have to be taken back if it doesn't fit
+        let before_insert = (inserts_text && self.max_len.is_some()).then(|| {
+            (
+                self.value.clone(),
+                self.cursor_char_idx,
+                self.selection_start_char_idx,
+                self.view_window.clone(),
+            )
+        });
+
+        self.update(msg);
+
+        if inserts_text {
+            self.normalize();
+        }
+
+        if let (
+            Some(max_len),
+            Some((value, cursor_char_idx, selection_start_char_idx, view_window)),
+        ) = (self.max_len, before_insert)
+        {
+            let len = self.value.chars().count();
+
+            // Edits that don't grow the value, like typing over a selection, are always allowed
+            if len > max_len && len > value.chars().count() {
+                self.value = value;
+                self.cursor_char_idx = cursor_char_idx;
+                self.selection_start_char_idx = selection_start_char_idx;
+                self.view_window = view_window;
+            }
+        }
+
+        // Following the tail must never hide the cursor, e.g. with auto pairs piling up after it
+        if follows_tail {
+            self.view_window.offsett = min(
+                (self.value.chars().count() + 1).saturating_sub(self.view_window.width),
+                self.cursor_char_idx,
+            );
+        }
+
+        let old_value = old_value.filter(|old_value| *old_value != self.value);
+        let changed = old_value.is_some();
+
+        if let Some(old_value) = old_value {
+            self.modified = true;
+
+            if self.change_debounce.is_some() {
+                // The callback is left to poll_change, which restarts the quiet period
+                self.change_pending = true;
+                self.change_seen_at = None;
+            } else {
+                self.fire_on_change();
+            }
+
+            // Rejected characters leave the value as it was, so they are never echoed
+            if let (Some(c), Some(echo)) = (typed_char, self.echo.0.as_mut()) {
+                echo(c);
+            }
+
+            // Only fire when the prefix appears, not on every change while it is there
+            if !old_value.starts_with(&self.match_prefix)
+                && self.value.starts_with(&self.match_prefix)
+            {
+                if let Some(on_match) = self.on_match.0.as_mut() {
+                    on_match();
+                }
+            }
+        }
+
+        if cfg!(debug_assertions) {
+            self.check_invariants();
+        }
+
+        changed
+    }
+
+    /// Update the [`InputState`] like [`InputState::handle_message`] and report what the message did to the value
+    pub fn handle_message_with_outcome(&mut self, msg: Message) -> MessageOutcome {
+        let at_boundary = self.selection_start_char_idx.is_none()
+            && match msg {
+                Message::DeleteBeforeCursor => self.cursor_char_idx == 0,
+                Message::DeleteOnCursor => self.cursor_char_idx == self.value.chars().count(),
+                _ => false,
+            };
+        let changed = self.apply_message(msg, true);
+
+        if at_boundary {
+            MessageOutcome::AtBoundary
+        } else if changed {
+            MessageOutcome::Changed
+        } else {
+            MessageOutcome::Unchanged
+        }
+    }
+
+    fn update(&mut self, msg: Message) {
         match msg {
-            Message::Empty => {}
-            Message::Focus => self.in_focus = true,
-            Message::RemoveFocus => self.in_focus = false,
+            // Already turned into a paste by handle_message
+            Message::Empty | Message::PasteFromClipboard => {}
+            Message::Focus => {
+                if self.select_all_on_focus && !self.in_focus {
+                    self.select_all_and_focus();
+                } else {
+                    self.in_focus = true;
+                }
+            }
+            Message::RemoveFocus => {
+                self.in_focus = false;
+
+                if let Some(on_blur) = self.on_blur.0.as_mut() {
+                    on_blur(&mut self.value);
+
+                    // The value might have changed, so the cursor and selection might not fit anymore
+                    self.clamp_to_value();
+                }
+            }
             Message::DeleteOnCursor => {
                 match self.selection() {
                     Some(selection) => {
@@ -83,8 +383,7 @@ impl InputState {
                         self.selection_start_char_idx = None;
 
                         // Cursor might fall outside the view window, so we move it to the left as needed
-                        self.view_window.offsett =
-                            min(self.view_window.offsett, self.cursor_char_idx);
+                        self.scroll_to_cursor();
                     }
                     None => {
                         if self.cursor_char_idx == self.value.chars().count() {
@@ -95,8 +394,7 @@ impl InputState {
                             let _ = self.value.remove(idx);
 
                             // Cursor might fall outside the view window, so we move it to the left as needed
-                            self.view_window.offsett =
-                                min(self.view_window.offsett, self.cursor_char_idx);
+                            self.scroll_to_cursor();
                         }
                     }
                 }
@@ -114,32 +412,102 @@ impl InputState {
                         self.selection_start_char_idx = None;
 
                         // Cursor might fall outside the view window, so we move it to the left as needed
-                        self.view_window.offsett =
-                            min(self.view_window.offsett, self.cursor_char_idx);
+                        self.scroll_to_cursor();
                     }
                     None => {
                         if self.cursor_char_idx == 0 {
                             // Do nothing because we are at the start
                         } else {
-                            let idx = self
-                                .value
-                                .char_indices()
-                                .enumerate()
-                                .find(|(char_idx, _)| char_idx == &(self.cursor_char_idx - 1))
-                                .map(|(_, (byte_idx, _))| byte_idx)
-                                .unwrap();
+                            if self.auto_pairs
+                                && self
+                                    .value
+                                    .chars()
+                                    .nth(self.cursor_char_idx - 1)
+                                    .and_then(closing_pair)
+                                    .is_some_and(|closer| {
+                                        self.value.chars().nth(self.cursor_char_idx) == Some(closer)
+                                    })
+                            {
+                                // We are inside an empty pair, so the closer goes away together with the opener
+                                let idx = char_idx_to_byte_idx(&self.value, self.cursor_char_idx);
+                                let _ = self.value.remove(idx);
+                            }
 
-                            let _ = self.value.remove(idx);
                             self.cursor_char_idx -= 1;
+                            let idx = char_idx_to_byte_idx(&self.value, self.cursor_char_idx);
+                            let _ = self.value.remove(idx);
 
                             // Cursor might fall outside the view window, so we move it to the left as needed
-                            self.view_window.offsett =
-                                min(self.view_window.offsett, self.cursor_char_idx);
+                            self.scroll_to_cursor();
                         }
                     }
                 }
             }
+            Message::DeleteForwardTo(target) => {
+                let Some(target_char_idx) = self
+                    .value
+                    .chars()
+                    .enumerate()
+                    .skip(self.cursor_char_idx + 1)
+                    .find(|(_, c)| *c == target)
+                    .map(|(char_idx, _)| char_idx)
+                else {
+                    // Nothing to delete up to
+                    return;
+                };
+
+                let start_idx = char_idx_to_byte_idx(&self.value, self.cursor_char_idx);
+                let end_idx = char_idx_to_byte_idx(&self.value, target_char_idx + 1);
+                self.value.replace_range(start_idx..end_idx, "");
+                self.selection_start_char_idx = None;
+            }
+            Message::DeleteBackTo(target) => {
+                let Some(target_char_idx) = self
+                    .value
+                    .chars()
+                    .take(self.cursor_char_idx)
+                    .enumerate()
+                    .filter(|(_, c)| *c == target)
+                    .map(|(char_idx, _)| char_idx)
+                    .last()
+                else {
+                    // Nothing to delete back to
+                    return;
+                };
+
+                let start_idx = char_idx_to_byte_idx(&self.value, target_char_idx);
+                let end_idx = char_idx_to_byte_idx(&self.value, self.cursor_char_idx);
+                self.value.replace_range(start_idx..end_idx, "");
+                self.selection_start_char_idx = None;
+
+                self.cursor_char_idx = target_char_idx;
+                self.scroll_to_cursor();
+            }
+            Message::DeleteWordBeforeCursor => {
+                if self.selection_start_char_idx.is_some() {
+                    // A selection is deleted as a whole, like with a regular backspace
+                    self.update(Message::DeleteBeforeCursor);
+                    return;
+                }
+
+                let word_start_char_idx = self.word_start_before_cursor();
+                let start_idx = char_idx_to_byte_idx(&self.value, word_start_char_idx);
+                let end_idx = char_idx_to_byte_idx(&self.value, self.cursor_char_idx);
+                self.value.replace_range(start_idx..end_idx, "");
+
+                self.cursor_char_idx = word_start_char_idx;
+                self.scroll_to_cursor();
+            }
             Message::MoveLeft => {
+                if let Some(selection) =
+                    self.selection().filter(|_| self.collapse_selection_to_edge)
+                {
+                    self.cursor_char_idx = selection.char_range.start;
+                    self.selection_start_char_idx = None;
+                    self.scroll_to_cursor();
+                    return;
+                }
+
                 // End selection
                 self.selection_start_char_idx = None;
 
@@ -147,10 +515,21 @@ impl InputState {
                     // We are at the start already so we cannot move anymore
                 } else {
                     self.cursor_char_idx -= 1;
-                    self.view_window.offsett = min(self.view_window.offsett, self.cursor_char_idx);
+                    self.scroll_to_cursor();
                 }
             }
             Message::MoveRight => {
+                if let Some(selection) =
+                    self.selection().filter(|_| self.collapse_selection_to_edge)
+                {
+                    // The cursor goes right behind the last selected character, but never past the end of the value
+                    self.cursor_char_idx =
+                        min(selection.char_range.end, self.value.chars().count());
+                    self.selection_start_char_idx = None;
+                    self.scroll_to_cursor();
+                    return;
+                }
+
                 // End selection
                 self.selection_start_char_idx = None;
 
@@ -158,23 +537,55 @@ impl InputState {
                     // We are already 1 step ahead of the value, so we cannot move anymore
                 } else {
                     self.cursor_char_idx += 1;
-                    if !self.view_window.contains(self.cursor_char_idx) {
-                        self.view_window.offsett =
-                            self.cursor_char_idx + 1 - self.view_window.width;
-                    }
+                    self.scroll_to_cursor();
                 }
             }
             Message::JumpToEnd => {
-                self.cursor_char_idx = self.value.chars().count();
-                if !self.view_window.contains(self.cursor_char_idx) {
-                    self.view_window.offsett = self.cursor_char_idx + 1 - self.view_window.width;
+                if self.collapse_selection_on_jump {
+                    // End selection
+                    self.selection_start_char_idx = None;
                 }
+
+                self.cursor_char_idx = self.value.chars().count();
+                self.scroll_to_cursor();
             }
             Message::JumpToStart => {
+                if self.collapse_selection_on_jump {
+                    // End selection
+                    self.selection_start_char_idx = None;
+                }
+
                 self.cursor_char_idx = 0;
-                self.view_window.offsett = min(self.view_window.offsett, self.cursor_char_idx);
+                self.scroll_to_cursor();
+            }
+            Message::Char('\n') if !self.multiline => {
+                // A single line input has no place for a newline
             }
             Message::Char(c) => {
+                // Tabs are text, newlines only when there can be more lines
+                if self.reject_control_chars
+                    && c.is_control()
+                    && c != '\t'
+                    && !(self.multiline && c == '\n')
+                {
+                    return;
+                }
+
+                if self.auto_pairs && self.selection_start_char_idx.is_none() && !self.insert_mode {
+                    if is_closing_pair(c) && self.value.chars().nth(self.cursor_char_idx) == Some(c)
+                    {
+                        // The closer is already in place, so we just step over it
+                        self.update(Message::MoveRight);
+                        return;
+                    }
+
+                    if let Some(closer) = closing_pair(c) {
+                        // Insert the closer after the cursor, the opener gets inserted before it below
+                        let idx = char_idx_to_byte_idx(&self.value, self.cursor_char_idx);
+                        self.value.insert(idx, closer);
+                    }
+                }
+
                 match self.selection() {
                     Some(selection) => {
                         // Replace the entire selection with the input
@@ -182,8 +593,7 @@ impl InputState {
                             .replace_range(selection.byte_range, c.to_string().as_str());
                         self.cursor_char_idx = selection.char_range.start + 1;
                         self.selection_start_char_idx = None;
-                        self.view_window.offsett =
-                            min(self.view_window.offsett, self.cursor_char_idx);
+                        self.scroll_to_cursor();
                     }
                     None => {
                         if self.cursor_char_idx == self.value.chars().count() {
@@ -203,7 +613,7 @@ impl InputState {
                                 .value
                                 .char_indices()
                                 .enumerate()
-                                .find(|(char_idx, _)| char_idx + 1 == self.cursor_char_idx)
+                                .find(|(char_idx, _)| char_idx == &(self.cursor_char_idx + 1))
                                 .map(|(_, (byte_idx, _))| byte_idx)
                                 .unwrap_or(self.value.len());
 
@@ -224,42 +634,141 @@ impl InputState {
                             self.value.insert(idx, c);
                         }
                         self.cursor_char_idx += 1;
-                        if !self.view_window.contains(self.cursor_char_idx) {
-                            self.view_window.offsett =
-                                self.cursor_char_idx + 1 - self.view_window.width;
-                        }
+                        self.scroll_right_to_cursor();
                     }
                 }
             }
-            Message::Paste(str) => match self.selection() {
-                Some(selection) => {
-                    self.value.replace_range(selection.byte_range, &str);
-                    self.cursor_char_idx = selection.char_range.end;
-                    self.selection_start_char_idx = None;
-                    self.view_window.offsett =
-                        if selection.text.chars().count() > str.chars().count() {
-                            // Replaced text was longer than pasted text, so view window moves left
-                            min(self.view_window.offsett, self.cursor_char_idx)
+            Message::Paste(str) => {
+                let str = if self.collapse_whitespace_on_paste {
+                    collapse_whitespace(&str, self.multiline)
+                } else {
+                    str
+                };
+
+                match self.selection() {
+                    Some(selection) => {
+                        self.value.replace_range(selection.byte_range, &str);
+                        self.cursor_char_idx = selection.char_range.start + str.chars().count();
+                        self.selection_start_char_idx = None;
+
+                        // Pasted text can be longer or shorter than the selection, so the view window might move either way
+                        self.scroll_to_cursor();
+                    }
+                    None => {
+                        if self.cursor_char_idx == self.value.chars().count() {
+                            self.value.push_str(str.as_str());
+                        } else if self.insert_mode {
+                            // Like typing, the pasted text goes over the characters from the cursor on, the rest is appended
+                            let start_idx = char_idx_to_byte_idx(&self.value, self.cursor_char_idx);
+                            let end_idx = char_idx_to_byte_idx(
+                                &self.value,
+                                self.cursor_char_idx + str.chars().count(),
+                            );
+                            self.value.replace_range(start_idx..end_idx, str.as_str());
                         } else {
-                            // Replaced text was shorter than pasted text, so view window moves right
-                            self.cursor_char_idx + 1 - self.view_window.width
-                        };
-                }
-                None => {
-                    if self.cursor_char_idx == self.value.chars().count() {
-                        self.value.push_str(str.as_str());
-                    } else {
-                        self.value
-                            .insert_str(self.cursor_char_idx() + 1, str.as_str());
+                            let idx = char_idx_to_byte_idx(&self.value, self.cursor_char_idx + 1);
+                            self.value.insert_str(idx, str.as_str());
+                        }
+                        self.cursor_char_idx += str.chars().count();
+                        self.scroll_right_to_cursor();
                     }
-                    self.cursor_char_idx += str.chars().count();
-                    if !self.view_window.contains(self.cursor_char_idx) {
-                        self.view_window.offsett =
-                            self.cursor_char_idx + 1 - self.view_window.width;
+                }
+            }
+            Message::CompleteNext | Message::CompletePrev => {
+                if self.candidates.is_empty() {
+                    return;
+                }
+
+                let mut completion = self.completion.take().unwrap_or_else(|| {
+                    // Only the word characters right before the cursor, right after a space the word is empty
+                    let before_cursor =
+                        &self.value[..char_idx_to_byte_idx(&self.value, self.cursor_char_idx)];
+                    let prefix_len = before_cursor
+                        .chars()
+                        .rev()
+                        .take_while(|c| self.word_boundary.is_word_char(*c))
+                        .count();
+
+                    Completion {
+                        start: self.cursor_char_idx - prefix_len,
+                        prefix: before_cursor
+                            .chars()
+                            .skip(self.cursor_char_idx - prefix_len)
+                            .collect(),
+                        index: None,
                     }
+                });
+
+                // The prefix sits in between the last and the first candidate
+                let count = self.candidates.len();
+                completion.index = match (msg, completion.index) {
+                    (Message::CompleteNext, None) => Some(0),
+                    (Message::CompleteNext, Some(idx)) if idx + 1 < count => Some(idx + 1),
+                    (Message::CompleteNext, Some(_)) => None,
+                    (_, None) => Some(count - 1),
+                    (_, Some(0)) => None,
+                    (_, Some(idx)) => Some(idx - 1),
+                };
+
+                let replacement: String = completion
+                    .index
+                    .map_or(&completion.prefix, |idx| &self.candidates[idx])
+                    .chars()
+                    .filter(|c| self.multiline || *c != '\n')
+                    .collect();
+                let start = char_idx_to_byte_idx(&self.value, completion.start);
+                let end = char_idx_to_byte_idx(&self.value, self.cursor_char_idx);
+                self.value.replace_range(start..end, &replacement);
+                self.cursor_char_idx = completion.start + replacement.chars().count();
+                self.selection_start_char_idx = None;
+                self.completion = Some(completion);
+
+                self.scroll_to_cursor();
+            }
+            Message::ReplaceChar(c) => {
+                if (self.multiline || c != '\n')
+                    && self.cursor_char_idx < self.value.chars().count()
+                {
+                    let start = char_idx_to_byte_idx(&self.value, self.cursor_char_idx);
+                    let end = char_idx_to_byte_idx(&self.value, self.cursor_char_idx + 1);
+                    self.value
+                        .replace_range(start..end, c.encode_utf8(&mut [0; 4]));
                 }
-            },
+            }
+            Message::Insert(str) => {
+                // A single line input has no place for a newline
+                let str: String = str
+                    .chars()
+                    .filter(|c| self.multiline || *c != '\n')
+                    .collect();
+                let str_len = str.chars().count();
+
+                let (byte_range, char_start) = match self.selection() {
+                    Some(selection) => (selection.byte_range, selection.char_range.start),
+                    None if self.insert_mode => {
+                        // Overwrite as many characters as we type, anything past the end gets appended
+                        let end_char_idx =
+                            min(self.cursor_char_idx + str_len, self.value.chars().count());
+                        (
+                            char_idx_to_byte_idx(&self.value, self.cursor_char_idx)
+                                ..char_idx_to_byte_idx(&self.value, end_char_idx),
+                            self.cursor_char_idx,
+                        )
+                    }
+                    None => {
+                        let idx = char_idx_to_byte_idx(&self.value, self.cursor_char_idx);
+                        (idx..idx, self.cursor_char_idx)
+                    }
+                };
+
+                self.value.replace_range(byte_range, &str);
+                self.cursor_char_idx = char_start + str_len;
+                self.selection_start_char_idx = None;
+
+                self.scroll_to_cursor();
+            }
             Message::ToggleInsertMode => self.insert_mode = !self.insert_mode,
+            Message::ToggleReveal => self.revealed = !self.revealed,
             Message::MoveLeftWithSelection => {
                 if self.cursor_char_idx == 0 {
                     // We are at the very, start and cannot move anywhere
@@ -285,11 +794,11 @@ impl InputState {
                     };
 
                     self.cursor_char_idx -= 1;
-                    self.view_window.offsett = min(self.view_window.offsett, self.cursor_char_idx);
+                    self.scroll_to_cursor();
                 }
             }
             Message::MoveRightWithSelection => {
-                if self.cursor_char_idx + 1 == self.value.chars().count() {
+                if self.cursor_char_idx + 1 >= self.value.chars().count() {
                     // Cannot move anymore
                 } else {
                     self.selection_start_char_idx = match self.selection_start_char_idx {
@@ -309,14 +818,29 @@ impl InputState {
                     };
 
                     self.cursor_char_idx += 1;
-                    if !self.view_window.contains(self.cursor_char_idx) {
-                        self.view_window.offsett =
-                            self.cursor_char_idx + 1 - self.view_window.width;
+                    self.scroll_to_cursor();
+                }
+            }
+            Message::SelectWordLeft => {
+                if self.cursor_char_idx == 0 {
+                    return;
+                }
+
+                if self.selection_start_char_idx.is_none() {
+                    if self.cursor_char_idx == self.value.chars().count() {
+                        self.selection_start_char_idx = Some(self.cursor_char_idx - 1);
+                    } else {
+                        self.selection_start_char_idx = Some(self.cursor_char_idx);
                     }
                 }
+
+                self.cursor_char_idx = self.word_start_before_cursor();
+                self.scroll_to_cursor();
             }
-            Message::JumpToEndWithSelection => {
-                if self.cursor_char_idx == self.value.chars().count() {
+            Message::SelectWordRight => {
+                let count = self.value.chars().count();
+                if self.cursor_char_idx + 1 >= count {
+                    // Cannot move anymore
                     return;
                 }
 
@@ -324,10 +848,59 @@ impl InputState {
                     self.selection_start_char_idx = Some(self.cursor_char_idx);
                 }
 
-                self.cursor_char_idx = self.value.chars().count() - 1;
-                if !self.view_window.contains(self.cursor_char_idx) {
-                    self.view_window.offsett = self.cursor_char_idx + 1 - self.view_window.width;
+                // Skip the gap after the cursor and then the word after it, landing on the word's last character
+                let chars = self.value.chars().collect::<Vec<_>>();
+                let mut char_idx = self.cursor_char_idx + 1;
+                while char_idx < count && !self.word_boundary.is_word_char(chars[char_idx]) {
+                    char_idx += 1;
+                }
+                while char_idx < count && self.word_boundary.is_word_char(chars[char_idx]) {
+                    char_idx += 1;
+                }
+
+                self.cursor_char_idx = char_idx - 1;
+                self.scroll_to_cursor();
+            }
+            Message::SetCursorAtColumn(column) => {
+                self.selection_start_char_idx = None;
+
+                self.cursor_char_idx = match self.hit_test(column) {
+                    Hit::Char(char_idx) => char_idx,
+                    Hit::Cursor => self.cursor_char_idx,
+                    Hit::Padding => self.value.chars().count(),
+                };
+                self.scroll_to_cursor();
+            }
+            Message::SelectAll => self.select_all(),
+            Message::ClearSelection => self.selection_start_char_idx = None,
+            Message::SelectToIndex(char_idx) => {
+                let len = self.value.chars().count();
+                if len == 0 {
+                    // Nothing to select
+                    return;
+                }
+
+                if self.selection_start_char_idx.is_none() {
+                    self.selection_start_char_idx = Some(min(self.cursor_char_idx, len - 1));
+                }
+
+                // The cursor is part of the selection, so it has to stay on a character
+                self.cursor_char_idx = min(char_idx, len - 1);
+                self.scroll_to_cursor();
+            }
+            Message::JumpToEndWithSelection => {
+                let count = self.value.chars().count();
+                if count == 0 || self.cursor_char_idx >= count {
+                    // Nothing to select between the cursor and the end
+                    return;
+                }
+
+                if self.selection_start_char_idx.is_none() {
+                    self.selection_start_char_idx = Some(self.cursor_char_idx);
                 }
+
+                self.cursor_char_idx = count - 1;
+                self.scroll_to_cursor();
             }
             Message::JumpToStartWithSelection => {
                 if self.cursor_char_idx == 0 {
@@ -343,26 +916,25 @@ impl InputState {
                 }
 
                 self.cursor_char_idx = 0;
-                self.view_window.offsett = min(self.view_window.offsett, self.cursor_char_idx);
+                self.scroll_to_cursor();
             }
-            Message::Copy => match self.selection() {
-                Some(selection) => {
-                    // Copy the selection
-                    let _ = clipboard::ClipboardContext::new()
-                        .and_then(|mut cc| cc.set_contents(selection.to_string()));
+            Message::Copy => {
+                if let Some(text) = self.copy_text() {
+                    self.set_clipboard_contents(text);
                 }
-                None => {
-                    // No selection, so we copy the entire value
-                    let _ = clipboard::ClipboardContext::new()
-                        .and_then(|mut cc| cc.set_contents(self.value.clone()));
-                }
-            },
+            }
             Message::Cut => {
+                // Text that can't be copied can't be cut either, it would be lost
+                if !self.has_clipboard() || self.masked_copy == MaskedCopy::Nothing {
+                    return;
+                }
+
                 match self.selection() {
                     Some(selection) => {
                         // Cut the selection and set cursor to the start of the selecion
-                        let _ = clipboard::ClipboardContext::new()
-                            .and_then(|mut cc| cc.set_contents(selection.to_string()));
+                        if let Some(text) = self.masked_copy_text(selection.to_string()) {
+                            self.set_clipboard_contents(text);
+                        }
                         self.cursor_char_idx = selection.char_range.start;
                         self.selection_start_char_idx = None;
                         let mut taken_iter = (0..self.value.chars().count())
@@ -371,476 +943,3811 @@ impl InputState {
                     }
                     None => {
                         // Copy the entire value and then clear it
-                        let _ = clipboard::ClipboardContext::new()
-                            .and_then(|mut cc| cc.set_contents(self.value.clone()));
+                        if let Some(text) = self.masked_copy_text(self.value.clone()) {
+                            self.set_clipboard_contents(text);
+                        }
                         self.value.clear();
                         self.cursor_char_idx = 0;
                         self.selection_start_char_idx = None;
                     }
                 };
-                self.view_window.offsett = min(self.view_window.offsett, self.cursor_char_idx);
+                self.scroll_to_cursor();
+            }
+        }
+    }
+
+    /// Update the [`InputState`] with a raw crossterm event, converted using the default key mappings
+    #[cfg(feature = "crossterm")]
+    pub fn handle_event(&mut self, event: Event) {
+        let msg = match event {
+            // A multiline input takes Enter as a newline instead of giving up the focus
+            Event::Key(key)
+                if self.multiline
+                    && key.code == KeyCode::Enter
+                    && key.kind != KeyEventKind::Release
+                    && key.modifiers == KeyModifiers::NONE =>
+            {
+                Message::Char('\n')
+            }
+            // Tab completes only when there is something to complete with
+            Event::Key(key)
+                if !self.candidates.is_empty()
+                    && key.kind != KeyEventKind::Release
+                    && matches!(key.code, KeyCode::Tab | KeyCode::BackTab) =>
+            {
+                if key.code == KeyCode::Tab {
+                    Message::CompleteNext
+                } else {
+                    Message::CompletePrev
+                }
+            }
+            event => event.into(),
+        };
+
+        self.handle_message(msg);
+    }
+
+    /// Message for a crossterm mouse event, given the area the [`crate::Input`] was rendered in.
+    /// A left click ends the selection and moves the cursor to the clicked character, with shift held the selection
+    /// is extended up to it instead. Dragging after the click selects from there up to the dragged over character
+    #[cfg(feature = "crossterm")]
+    pub fn mouse_message(&self, event: MouseEvent, area: Rect) -> Message {
+        let on_input =
+            event.row == area.y && event.column >= area.x && event.column - area.x < area.width;
+
+        if !on_input {
+            return Message::Empty;
+        }
+
+        let column = (event.column - area.x) as usize;
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) if event.modifiers != KeyModifiers::SHIFT => {
+                Message::SetCursorAtColumn(column)
+            }
+            MouseEventKind::Down(MouseButton::Left) | MouseEventKind::Drag(MouseButton::Left) => {
+                Message::SelectToIndex(self.char_idx_at_column(column))
             }
+            _ => Message::Empty,
         }
     }
 
+    /// Update the [`InputState`] with a crossterm mouse event, see [`InputState::mouse_message`]
+    #[cfg(feature = "crossterm")]
+    pub fn handle_mouse_event(&mut self, event: MouseEvent, area: Rect) {
+        let msg = self.mouse_message(event, area);
+        self.handle_message(msg);
+    }
+
     /// Current value of the input
     pub fn text(&self) -> &str {
         &self.value
     }
 
-    #[allow(unused)]
-    pub(crate) fn cursor_byte_idx(&self) -> usize {
-        char_idx_to_byte_idx(&self.value, self.cursor_char_idx)
+    /// Number of terminal columns needed to display the whole value. Every character is counted as one column,
+    /// except for tabs taking up as many as the widget last drew them with, like in [`crate::Input::desired_width`]
+    pub fn display_width(&self) -> usize {
+        self.value
+            .chars()
+            .map(|c| cell_width(c, self.tab_width))
+            .sum()
     }
 
-    pub(crate) fn cursor_char_idx(&self) -> usize {
-        self.cursor_char_idx
+    /// Whether a message changed the value since the input was created, reset or [`InputState::set_modified`] cleared it.
+    /// Moving the cursor, selecting or toggling the insert mode don't count, neither does [`InputState::set_text`]
+    pub fn is_modified(&self) -> bool {
+        self.modified
     }
 
-    /// Currently selected text
-    pub fn selection(&self) -> Option<Selection> {
-        match self.selection_start_char_idx {
-            Some(start_char_idx) => {
-                let min_char_idx = min(start_char_idx, self.cursor_char_idx);
-                let min_byte_idx = char_idx_to_byte_idx(&self.value, min_char_idx);
-                let max_char_idx = max(start_char_idx, self.cursor_char_idx) + 1;
-                let max_byte_idx = char_idx_to_byte_idx(&self.value, max_char_idx);
+    /// Set or clear the modified flag, e.g. clear it once the value is saved
+    pub fn set_modified(&mut self, modified: bool) {
+        self.modified = modified;
+    }
 
-                Some(Selection {
-                    char_range: min_char_idx..max_char_idx,
-                    byte_range: min_byte_idx..max_byte_idx,
-                    text: self.value[min_byte_idx..max_byte_idx].to_string(),
-                })
-            }
-            None => None,
-        }
+    /// Replace the value of the input, moving the cursor to the end and ending the selection.
+    /// Line breaks are stored as a plain `\n`, unless the input is single line, in which case they are dropped
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.value = text.into();
+        let multiline = self.multiline;
+        self.value.retain(|c| c != '\r' && (multiline || c != '\n'));
+
+        // The old selection and completion might not fit the new value anymore
+        self.selection_start_char_idx = None;
+        self.completion = None;
+
+        self.cursor_char_idx = self.value.chars().count();
+        self.scroll_to_cursor();
     }
-}
 
-fn char_idx_to_byte_idx(str: &str, char_idx: usize) -> usize {
-    str.char_indices()
-        .enumerate()
-        .find(|(idx, _)| idx == &char_idx)
+    /// Load a fresh value like [`InputState::set_text`], also dropping what was left over from editing the old one:
+    /// the modified flag, highlights, the preedit, a completion in progress and a change the debounced callback has yet to report
+    pub fn reset_with(&mut self, value: impl Into<String>) {
+        self.set_text(value);
+
+        self.modified = false;
+        self.highlights.clear();
+        self.preedit = None;
+        self.completion = None;
+        self.change_pending = false;
+        self.change_seen_at = None;
+    }
+
+    /// Replace the value of the input like [`InputState::set_text`], but keep the cursor where it was if the new value is long enough
+    pub fn set_text_keep_cursor(&mut self, text: impl Into<String>) {
+        let cursor_char_idx = self.cursor_char_idx;
+
+        self.set_text(text);
+
+        // set_text left the cursor at the end of the new value
+        self.cursor_char_idx = min(cursor_char_idx, self.cursor_char_idx);
+        self.scroll_to_cursor();
+    }
+
+    /// Move the cursor by the given number of characters, clamped to the value. Ends the selection
+    pub fn move_cursor(&mut self, delta: isize) {
+        self.selection_start_char_idx = None;
+        self.completion = None;
+
+        self.cursor_char_idx = min(
+            self.cursor_char_idx.saturating_add_signed(delta),
+            self.value.chars().count(),
+        );
+        self.scroll_to_cursor();
+    }
+
+    /// Whether typed characters overwrite the character under the cursor instead of being inserted
+    pub fn insert_mode(&self) -> bool {
+        self.insert_mode
+    }
+
+    /// Whether the widget shows the value as it is instead of masking it, see [`Message::ToggleReveal`]
+    pub fn is_revealed(&self) -> bool {
+        self.revealed
+    }
+
+    /// Show the value as it is or mask it again, like [`Message::ToggleReveal`]
+    pub fn set_revealed(&mut self, revealed: bool) {
+        self.revealed = revealed;
+    }
+
+    /// Fraction of the value scrolled out of view on the left and the fraction that is visible,
+    /// e.g. for drawing a horizontal scroll indicator
+    pub fn scroll_ratio(&self) -> (f64, f64) {
+        let len = self.value.chars().count();
+        if len == 0 {
+            // Nothing to scroll, everything there is fits
+            return (0.0, 1.0);
+        }
+
+        let start = (self.view_window.offsett as f64 / len as f64).min(1.0);
+        let visible = (self.view_window.width as f64 / len as f64).min(1.0 - start);
+
+        (start, visible)
+    }
+
+    /// Move the view window just enough for the cursor to be visible, e.g. after changing the value from the outside
+    pub fn scroll_to_cursor(&mut self) {
+        self.view_window.offsett = min(self.view_window.offsett, self.cursor_char_idx);
+        if !self.view_window.contains(self.cursor_char_idx) {
+            self.view_window.offsett = self.cursor_char_idx + 1 - self.view_window.width;
+        }
+    }
+
+    /// Move the view window right for the cursor to be visible after typing, by whole scroll steps
+    fn scroll_right_to_cursor(&mut self) {
+        if self.view_window.contains(self.cursor_char_idx) {
+            return;
+        }
+
+        // Left of the window, so there is nothing to scroll right for
+        if self.cursor_char_idx < self.view_window.offsett {
+            self.scroll_to_cursor();
+            return;
+        }
+
+        let shift = self.cursor_char_idx + 1 - self.view_window.width - self.view_window.offsett;
+        let step = max(self.scroll_animation_step, 1);
+        let shift = shift.div_ceil(step) * step;
+
+        // Scrolling further than the cursor would hide it
+        self.view_window.offsett = min(self.view_window.offsett + shift, self.cursor_char_idx);
+    }
+
+    /// Whether the input is currently in focus
+    pub fn is_focused(&self) -> bool {
+        self.in_focus
+    }
+
+    /// Focus or unfocus the input like [`Message::Focus`] and [`Message::RemoveFocus`] would,
+    /// returning whether it was focused before. Nothing happens if the focus stays the same
+    pub fn set_focus(&mut self, focus: bool) -> bool {
+        let was_focused = self.in_focus;
+
+        if focus != was_focused {
+            self.handle_message(if focus {
+                Message::Focus
+            } else {
+                Message::RemoveFocus
+            });
+        }
+
+        was_focused
+    }
+
+    /// Character ranges of all non-overlapping occurrences of `needle` inside the value
+    pub fn find_all(&self, needle: &str, case_insensitive: bool) -> Vec<Range<usize>> {
+        let haystack = self.value.chars().collect::<Vec<_>>();
+        let needle = needle.chars().collect::<Vec<_>>();
+        let mut matches = Vec::new();
+
+        if needle.is_empty() {
+            return matches;
+        }
+
+        let mut char_idx = 0;
+        while char_idx + needle.len() <= haystack.len() {
+            let is_match = haystack[char_idx..char_idx + needle.len()]
+                .iter()
+                .zip(needle.iter())
+                .all(|(a, b)| {
+                    if case_insensitive {
+                        a.to_lowercase().eq(b.to_lowercase())
+                    } else {
+                        a == b
+                    }
+                });
+
+            if is_match {
+                // Continue after the match, so matches never overlap
+                matches.push(char_idx..char_idx + needle.len());
+                char_idx += needle.len();
+            } else {
+                char_idx += 1;
+            }
+        }
+
+        matches
+    }
+
+    /// Replace every occurrence of `from` with `to`, returning the number of replacements.
+    /// The cursor stays on the same text, or moves to the start of the replacement if it was inside a match
+    pub fn replace_all(&mut self, from: &str, to: &str) -> usize {
+        let matches = self.find_all(from, false);
+        if matches.is_empty() {
+            return 0;
+        }
+
+        let to_len = to.chars().count();
+        let mut shift = 0isize;
+        let mut cursor_char_idx = None;
+        for range in &matches {
+            if range.end <= self.cursor_char_idx {
+                // Matches in front of the cursor move it by the difference in length
+                shift += to_len as isize - range.len() as isize;
+            } else {
+                if range.start < self.cursor_char_idx {
+                    cursor_char_idx = Some(range.start.saturating_add_signed(shift));
+                }
+                break;
+            }
+        }
+
+        self.value = self.value.replace(from, to);
+        self.cursor_char_idx =
+            cursor_char_idx.unwrap_or(self.cursor_char_idx.saturating_add_signed(shift));
+        self.selection_start_char_idx = None;
+        self.completion = None;
+
+        self.clamp_to_value();
+        self.scroll_to_cursor();
+
+        matches.len()
+    }
+
+    /// Replace the selection with the result of `f` on it, e.g. to uppercase it, returning whether there was a selection.
+    /// The selection ends and the cursor moves to the end of the replacement
+    pub fn map_selection(&mut self, f: impl Fn(&str) -> String) -> bool {
+        let Some(selection) = self.selection() else {
+            return false;
+        };
+
+        let replacement = f(&selection);
+        self.value.replace_range(selection.byte_range, &replacement);
+        self.cursor_char_idx = selection.char_range.start + replacement.chars().count();
+        self.selection_start_char_idx = None;
+        self.completion = None;
+
+        self.clamp_to_value();
+        self.scroll_to_cursor();
+
+        true
+    }
+
+    /// Character ranges rendered with the highlight style, e.g. the result of [`InputState::find_all`]
+    pub fn highlights(&self) -> &[Range<usize>] {
+        &self.highlights
+    }
+
+    /// Set the character ranges to render with the highlight style
+    pub fn set_highlights(&mut self, highlights: Vec<Range<usize>>) {
+        self.highlights = highlights;
+    }
+
+    /// Uncommitted text of an input method, displayed at the cursor but not part of the value
+    pub fn preedit(&self) -> Option<&str> {
+        self.preedit.as_deref()
+    }
+
+    /// Set or clear the uncommitted text of an input method
+    pub fn set_preedit(&mut self, preedit: Option<String>) {
+        self.preedit = preedit.filter(|preedit| !preedit.is_empty());
+    }
+
+    /// Insert the preedit text at the cursor as if it was typed
+    pub fn commit_preedit(&mut self) {
+        if let Some(preedit) = self.preedit.take() {
+            for c in preedit.chars() {
+                self.handle_message(Message::Char(c));
+            }
+        }
+    }
+
+    /// Focus the input and select its entire value, so the next keystroke replaces it
+    pub fn select_all_and_focus(&mut self) {
+        self.in_focus = true;
+        self.select_all();
+    }
+
+    /// Select the entire value, anchored at the start with the cursor on the last character
+    fn select_all(&mut self) {
+        let len = self.value.chars().count();
+        self.completion = None;
+        if len == 0 {
+            // Nothing to select
+            self.selection_start_char_idx = None;
+            self.cursor_char_idx = 0;
+            self.view_window.offsett = 0;
+        } else {
+            self.selection_start_char_idx = Some(0);
+            self.cursor_char_idx = len - 1;
+            // The window might be past the last character when the cursor was at the end
+            self.scroll_to_cursor();
+        }
+    }
+
+    /// Select the entire value whenever the input gains focus
+    pub fn set_select_all_on_focus(&mut self, select_all_on_focus: bool) {
+        self.select_all_on_focus = select_all_on_focus;
+    }
+
+    /// End the selection when jumping to the start or end without selecting, instead of extending it
+    pub fn set_collapse_selection_on_jump(&mut self, collapse_selection_on_jump: bool) {
+        self.collapse_selection_on_jump = collapse_selection_on_jump;
+    }
+
+    /// Set what [`Message::Copy`] copies when nothing is selected
+    pub fn set_copy_fallback(&mut self, copy_fallback: CopyFallback) {
+        self.copy_fallback = copy_fallback;
+    }
+
+    /// Use the given clipboard instead of the system one, e.g. one backed by the browser in a WebAssembly build
+    /// or a fake one in tests
+    pub fn set_clipboard(&mut self, clipboard: Box<dyn Clipboard>) {
+        self.clipboard = Hook(Some(clipboard));
+    }
+
+    /// Set what [`Message::Copy`] and [`Message::Cut`] put on the clipboard in place of the plaintext, e.g. for a password
+    pub fn set_masked_copy(&mut self, masked_copy: MaskedCopy) {
+        self.masked_copy = masked_copy;
+    }
+
+    /// Moving left or right without selecting ends the selection with the cursor on its left or right edge,
+    /// instead of moving one character from where the cursor is
+    pub fn set_collapse_selection_to_edge(&mut self, collapse_selection_to_edge: bool) {
+        self.collapse_selection_to_edge = collapse_selection_to_edge;
+    }
+
+    /// Set which characters make up a word
+    pub fn set_word_boundary(&mut self, word_boundary: WordBoundary) {
+        self.word_boundary = word_boundary;
+    }
+
+    /// Whether the characters before and under the cursor differ in being part of a word.
+    /// The start and end of the value are always boundaries
+    pub fn at_word_boundary(&self) -> bool {
+        if self.cursor_char_idx == 0 {
+            return true;
+        }
+
+        let mut chars = self.value.chars().skip(self.cursor_char_idx - 1);
+        match (chars.next(), chars.next()) {
+            (Some(before), Some(on)) => {
+                self.word_boundary.is_word_char(before) != self.word_boundary.is_word_char(on)
+            }
+            _ => true,
+        }
+    }
+
+    /// Set the candidates [`Message::CompleteNext`] and [`Message::CompletePrev`] cycle through.
+    /// While there are any, Tab and Shift+Tab complete in [`InputState::handle_event`]
+    pub fn set_candidates(&mut self, candidates: Vec<String>) {
+        self.candidates = candidates;
+        self.completion = None;
+    }
+
+    /// Number of characters the value starts with that the candidate starts with too, e.g. for ranking completions
+    pub fn common_prefix_len(&self, candidate: &str) -> usize {
+        self.value
+            .chars()
+            .zip(candidate.chars())
+            .take_while(|(a, b)| a == b)
+            .count()
+    }
+
+    /// Char ranges and slices of the words in the value, as set by [`InputState::set_word_boundary`]
+    pub fn words(&self) -> impl Iterator<Item = (Range<usize>, &str)> {
+        let word_boundary = &self.word_boundary;
+        let mut chars = self.value.char_indices().enumerate().peekable();
+
+        std::iter::from_fn(move || {
+            let (start, start_byte) = loop {
+                match chars.next()? {
+                    (char_idx, (byte_idx, c)) if word_boundary.is_word_char(c) => {
+                        break (char_idx, byte_idx)
+                    }
+                    _ => {}
+                }
+            };
+
+            let mut end = start + 1;
+            let mut end_byte = self.value.len();
+            while let Some(&(char_idx, (byte_idx, c))) = chars.peek() {
+                if !word_boundary.is_word_char(c) {
+                    end_byte = byte_idx;
+                    break;
+                }
+                end = char_idx + 1;
+                let _ = chars.next();
+            }
+
+            Some((start..end, &self.value[start_byte..end_byte]))
+        })
+    }
+
+    /// Set a callback that normalizes the value whenever the input loses focus, e.g. trimming whitespace
+    pub fn set_on_blur(&mut self, on_blur: Box<dyn FnMut(&mut String)>) {
+        self.on_blur = Hook(Some(on_blur));
+    }
+
+    /// Set a callback that is called with the new value whenever a message changes it
+    pub fn set_on_change(&mut self, on_change: Box<OnChangeFn>) {
+        self.on_change = Hook(Some(on_change));
+    }
+
+    /// Only call the change callback once the value stopped changing for the given time, [`None`] calls it right away.
+    /// The waiting is driven by [`InputState::poll_change`]
+    pub fn set_change_debounce(&mut self, change_debounce: Option<Duration>) {
+        self.change_debounce = change_debounce;
+    }
+
+    /// Call the debounced change callback if the value changed and has been quiet for long enough.
+    /// Call it regularly, e.g. on every tick of the event loop. The quiet period starts at the first poll after a change
+    pub fn poll_change(&mut self, now: Instant) {
+        let Some(change_debounce) = self.change_debounce else {
+            return;
+        };
+
+        if !self.change_pending {
+            return;
+        }
+
+        match self.change_seen_at {
+            None => self.change_seen_at = Some(now),
+            Some(change_seen_at) => {
+                if now.saturating_duration_since(change_seen_at) >= change_debounce {
+                    self.change_pending = false;
+                    self.change_seen_at = None;
+                    self.fire_on_change();
+                }
+            }
+        }
+    }
+
+    fn fire_on_change(&mut self) {
+        let info = self.info();
+        if let Some(on_change) = self.on_change.0.as_mut() {
+            on_change(&self.value, info);
+        }
+    }
+
+    /// Keep the view window at least this wide when rendering into a narrower area, the part that doesn't fit is clipped
+    pub fn set_min_view_width(&mut self, min_view_width: usize) {
+        self.min_view_width = min_view_width;
+    }
+
+    /// Allow newlines in the value. Enter then inserts a newline through [`InputState::handle_event`] instead of removing the focus
+    pub fn set_multiline(&mut self, multiline: bool) {
+        self.multiline = multiline;
+    }
+
+    /// Keep the end of the value in view as text is typed or pasted, even when the cursor is elsewhere.
+    /// Once the view is moved away from the end, e.g. by moving the cursor, it scrolls with the cursor again
+    pub fn set_follow_tail(&mut self, follow_tail: bool) {
+        self.follow_tail = follow_tail;
+    }
+
+    /// Number of characters the view window scrolls by at least when typing or pasting moves the cursor past its right edge,
+    /// leaving room for the text that follows instead of scrolling one character at a time. Never scrolls past the cursor
+    pub fn set_scroll_animation_step(&mut self, scroll_animation_step: usize) {
+        self.scroll_animation_step = scroll_animation_step;
+    }
+
+    /// Turn every run of whitespace in pasted text into a single space, e.g. for a search field.
+    /// In a multiline input a run with a newline becomes a newline instead
+    pub fn set_collapse_whitespace_on_paste(&mut self, collapse_whitespace_on_paste: bool) {
+        self.collapse_whitespace_on_paste = collapse_whitespace_on_paste;
+    }
+
+    /// Ignore typed control characters like BEL, except tabs and newlines in a multiline input
+    pub fn set_reject_control_chars(&mut self, reject_control_chars: bool) {
+        self.reject_control_chars = reject_control_chars;
+    }
+
+    /// Set the maximum number of characters that can be typed or pasted, [`None`] for no limit.
+    /// Input that would grow the value past the limit is ignored as a whole
+    pub fn set_max_len(&mut self, max_len: Option<usize>) {
+        self.max_len = max_len;
+    }
+
+    /// Set a callback that is called when a message makes the value start with the given prefix,
+    /// e.g. `/` for opening a command menu in a chat input
+    pub fn set_on_match(&mut self, prefix: impl Into<String>, on_match: Box<dyn FnMut()>) {
+        self.match_prefix = prefix.into();
+        self.on_match = Hook(Some(on_match));
+    }
+
+    /// Set a callback that is called with every typed character that made it into the value, e.g. for logging keystrokes
+    pub fn set_echo(&mut self, echo: Option<Box<dyn FnMut(char)>>) {
+        self.echo = Hook(echo);
+    }
+
+    /// Set the normalization form that typed and pasted text is stored in, [`None`] keeps the text as is
+    pub fn set_normalization(&mut self, normalization: Option<Normalization>) {
+        self.normalization = normalization;
+    }
+
+    /// Enable or disable automatic insertion of closing brackets and quotes
+    pub fn set_auto_pairs(&mut self, auto_pairs: bool) {
+        self.auto_pairs = auto_pairs;
+    }
+
+    pub(crate) fn cursor_byte_idx(&self) -> usize {
+        char_idx_to_byte_idx(&self.value, self.cursor_char_idx)
+    }
+
+    /// Byte range of the character under the cursor, [`None`] when the cursor is past the end of the value
+    pub fn cursor_char_byte_range(&self) -> Option<Range<usize>> {
+        let start = self.cursor_byte_idx();
+        self.value[start..]
+            .chars()
+            .next()
+            .map(|c| start..start + c.len_utf8())
+    }
+
+    pub(crate) fn cursor_char_idx(&self) -> usize {
+        self.cursor_char_idx
+    }
+
+    /// Verify that the cursor, selection and view window all fall within the value
+    fn check_invariants(&self) {
+        let len = self.value.chars().count();
+
+        debug_assert!(
+            self.cursor_char_idx <= len,
+            "cursor {} is past the end of the value {:?}",
+            self.cursor_char_idx,
+            self.value
+        );
+        debug_assert!(
+            self.selection_start_char_idx
+                .map_or(true, |selection_start_char_idx| selection_start_char_idx
+                    <= len),
+            "selection start {:?} is past the end of the value {:?}",
+            self.selection_start_char_idx,
+            self.value
+        );
+        debug_assert!(
+            self.view_window.offsett <= len,
+            "view window offsett {} is past the end of the value {:?}",
+            self.view_window.offsett,
+            self.value
+        );
+    }
+
+    /// Keep the cursor, selection and view window within the value after it was changed from the outside
+    fn clamp_to_value(&mut self) {
+        let len = self.value.chars().count();
+
+        self.cursor_char_idx = min(self.cursor_char_idx, len);
+        self.selection_start_char_idx = self
+            .selection_start_char_idx
+            .filter(|selection_start_char_idx| *selection_start_char_idx < len);
+        self.scroll_to_cursor();
+    }
+
+    /// Bring the value into the configured normalization form
+    fn normalize(&mut self) {
+        if let Some(normalization) = self.normalization {
+            // Characters can merge or split, so the cursor stays behind the same text instead of the same index
+            let cursor_byte_idx = self.cursor_byte_idx();
+            self.cursor_char_idx = normalization
+                .normalize(&self.value[..cursor_byte_idx])
+                .chars()
+                .count();
+            self.value = normalization.normalize(&self.value);
+
+            self.clamp_to_value();
+            self.scroll_to_cursor();
+        }
+    }
+
+    /// Number of characters in the value
+    pub fn len_chars(&self) -> usize {
+        self.value.chars().count()
+    }
+
+    /// Number of bytes in the value
+    pub fn len_bytes(&self) -> usize {
+        self.value.len()
+    }
+
+    /// Number of lines in the value, 1 unless it contains newlines
+    pub fn line_count(&self) -> usize {
+        self.value.matches('\n').count() + 1
+    }
+
+    /// Line of the value the cursor is on, without the newline. The whole value unless it contains newlines
+    pub fn current_line(&self) -> &str {
+        let cursor_byte_idx = self.cursor_byte_idx();
+        let start = self.value[..cursor_byte_idx]
+            .rfind('\n')
+            .map_or(0, |idx| idx + 1);
+        let end = self.value[cursor_byte_idx..]
+            .find('\n')
+            .map_or(self.value.len(), |idx| cursor_byte_idx + idx);
+
+        &self.value[start..end]
+    }
+
+    /// Summary of the current value
+    pub fn info(&self) -> InputInfo {
+        InputInfo {
+            len_chars: self.len_chars(),
+            len_bytes: self.len_bytes(),
+            has_selection: self.has_selection(),
+        }
+    }
+
+    /// Whether there is a clipboard to copy to
+    fn has_clipboard(&self) -> bool {
+        self.clipboard.0.is_some()
+    }
+
+    fn clipboard_contents(&mut self) -> Option<String> {
+        self.clipboard
+            .0
+            .as_mut()
+            .and_then(|clipboard| clipboard.get())
+    }
+
+    fn set_clipboard_contents(&mut self, text: String) {
+        if let Some(clipboard) = self.clipboard.0.as_mut() {
+            clipboard.set(text);
+        }
+    }
+
+    /// Text [`Message::Copy`] puts on the clipboard, the selection or the [`CopyFallback`] without one
+    fn copy_text(&self) -> Option<String> {
+        match self.selection() {
+            Some(selection) => self.masked_copy_text(selection.to_string()),
+            None => self
+                .copy_fallback_text()
+                .and_then(|text| self.masked_copy_text(text)),
+        }
+    }
+
+    /// Text as it goes on the clipboard according to the [`MaskedCopy`]
+    fn masked_copy_text(&self, text: String) -> Option<String> {
+        match self.masked_copy {
+            MaskedCopy::Plaintext => Some(text),
+            MaskedCopy::Nothing => None,
+            MaskedCopy::Mask(mask) => Some(text.chars().map(|_| mask).collect()),
+        }
+    }
+
+    fn copy_fallback_text(&self) -> Option<String> {
+        match self.copy_fallback {
+            CopyFallback::EntireValue => Some(self.value.clone()),
+            CopyFallback::CurrentWord => {
+                let chars = self.value.chars().collect::<Vec<_>>();
+                let mut start = self.cursor_char_idx;
+                while start > 0 && self.word_boundary.is_word_char(chars[start - 1]) {
+                    start -= 1;
+                }
+                let mut end = self.cursor_char_idx;
+                while end < chars.len() && self.word_boundary.is_word_char(chars[end]) {
+                    end += 1;
+                }
+
+                (start < end).then(|| chars[start..end].iter().collect())
+            }
+            CopyFallback::Nothing => None,
+        }
+    }
+
+    /// Start of the word before the cursor, skipping any non-word characters directly in front of it
+    fn word_start_before_cursor(&self) -> usize {
+        let chars = self.value.chars().collect::<Vec<_>>();
+        let mut char_idx = self.cursor_char_idx;
+        while char_idx > 0 && !self.word_boundary.is_word_char(chars[char_idx - 1]) {
+            char_idx -= 1;
+        }
+        while char_idx > 0 && self.word_boundary.is_word_char(chars[char_idx - 1]) {
+            char_idx -= 1;
+        }
+
+        char_idx
+    }
+
+    /// Whether any text is selected
+    pub fn has_selection(&self) -> bool {
+        self.selection_start_char_idx.is_some()
+    }
+
+    /// Which end of the selection the cursor is on, [`None`] when nothing is selected
+    pub fn selection_direction(&self) -> Option<SelectionDirection> {
+        self.selection_start_char_idx
+            .map(|selection_start_char_idx| {
+                if selection_start_char_idx <= self.cursor_char_idx {
+                    SelectionDirection::Forward
+                } else {
+                    SelectionDirection::Backward
+                }
+            })
+    }
+
+    /// What is drawn in the given column of the view window. The preedit is drawn in front of the cursor, so it counts
+    /// as the cursor
+    pub fn hit_test(&self, column: usize) -> Hit {
+        let Some(position) = self.cell_positions_in_view().get(column).copied() else {
+            return Hit::Padding;
+        };
+
+        match self.position_char_idx(position) {
+            None => Hit::Cursor,
+            Some(char_idx) if char_idx == self.cursor_char_idx => Hit::Cursor,
+            Some(char_idx) if char_idx < self.value.chars().count() => Hit::Char(char_idx),
+            Some(_) => Hit::Padding,
+        }
+    }
+
+    /// Escape sequence (`ESC[row;colH`) moving the terminal cursor onto the input cursor, given the area the
+    /// [`crate::Input`] was rendered in, for using the terminal's own blinking cursor. [`None`] if the cursor is out of view
+    pub fn cursor_report(&self, area: Rect) -> Option<String> {
+        let (x, y) = self.screen_cursor_position(area)?;
+
+        // Terminal rows and columns count from 1
+        Some(format!("\x1b[{};{}H", y as usize + 1, x as usize + 1))
+    }
+
+    /// Screen column and row of the cursor, given the area the [`crate::Input`] was rendered in.
+    /// [`None`] if the cursor is out of view
+    pub fn screen_cursor_position(&self, area: Rect) -> Option<(u16, u16)> {
+        if area.height == 0 {
+            return None;
+        }
+
+        // Behind the preedit, on the first cell of a tab
+        let cursor_position = self.cursor_char_idx + self.preedit_len();
+        let column = self
+            .cell_positions_in_view()
+            .into_iter()
+            .position(|position| position == cursor_position)?;
+        if column >= area.width as usize {
+            return None;
+        }
+
+        Some((area.x + column as u16, area.y))
+    }
+
+    /// Index and value of the character in the given column of the view window,
+    /// [`None`] on the preedit and past the end of the value
+    pub fn char_at_column(&self, column: usize) -> Option<(usize, char)> {
+        let position = self.cell_positions_in_view().get(column).copied()?;
+        let char_idx = self.position_char_idx(position)?;
+        self.value.chars().nth(char_idx).map(|c| (char_idx, c))
+    }
+
+    /// Character index for the given column of the view window, the cursor on the preedit and past the window
+    /// the end of the value
+    #[cfg(feature = "crossterm")]
+    fn char_idx_at_column(&self, column: usize) -> usize {
+        match self.cell_positions_in_view().get(column) {
+            Some(position) => self
+                .position_char_idx(*position)
+                .unwrap_or(self.cursor_char_idx),
+            None => self.value.chars().count(),
+        }
+    }
+
+    /// Display positions of the cells of the view window, see [`InputState::cell_positions`]
+    fn cell_positions_in_view(&self) -> Vec<usize> {
+        self.cell_positions(&self.view_window, self.view_window.width, self.tab_width)
+    }
+
+    /// Display position of every cell of the view window the way the widget lays them out, `width` cells at most.
+    /// Display positions count the preedit drawn in front of the cursor, all cells of a tab share its position.
+    /// They continue past the text, so the cursor can be drawn behind it
+    pub(crate) fn cell_positions(
+        &self,
+        view_window: &ViewWindow,
+        width: usize,
+        tab_width: usize,
+    ) -> Vec<usize> {
+        // The window can be wider than the area, cells past the area would be clipped anyway so they are not built
+        let cells_width = min(view_window.width, width);
+        let preedit = self.preedit.as_deref().unwrap_or_default();
+        let cursor_byte_idx = self.cursor_byte_idx();
+        let widths = [
+            &self.value[..cursor_byte_idx],
+            preedit,
+            &self.value[cursor_byte_idx..],
+        ]
+        .concat()
+        .chars()
+        .map(|c| cell_width(c, tab_width))
+        .collect::<Vec<_>>();
+
+        // Preedit is displayed in front of the cursor, so the window might need to move further right
+        let cursor_position = self.cursor_char_idx + self.preedit_len();
+        let offsett = if preedit.is_empty() {
+            view_window.offsett
+        } else {
+            max(
+                view_window.offsett,
+                (cursor_position + 1).saturating_sub(view_window.width),
+            )
+        };
+        let offsett = fit_cursor(&widths, offsett, cursor_position, view_window.width);
+
+        let mut positions = Vec::with_capacity(cells_width);
+        for (position, width) in widths.iter().enumerate().skip(offsett) {
+            if positions.len() >= cells_width {
+                break;
+            }
+            positions.extend((0..*width).map(|_| position));
+        }
+        positions.truncate(cells_width);
+
+        let mut position = max(offsett, widths.len());
+        while positions.len() < cells_width {
+            positions.push(position);
+            position += 1;
+        }
+
+        positions
+    }
+
+    /// Move the view window right as far as the tabs in front of the cursor need for it to fit, given the cells a tab
+    /// takes up. Called by the widget, so every column mapping agrees with what it draws
+    pub(crate) fn fit_tabs(&mut self, tab_width: usize) {
+        self.tab_width = tab_width;
+
+        let widths = self
+            .value
+            .chars()
+            .map(|c| cell_width(c, tab_width))
+            .collect::<Vec<_>>();
+        self.view_window.offsett = fit_cursor(
+            &widths,
+            self.view_window.offsett,
+            self.cursor_char_idx,
+            self.view_window.width,
+        );
+    }
+
+    /// Character index of a display position, [`None`] on the preedit
+    fn position_char_idx(&self, position: usize) -> Option<usize> {
+        let preedit_len = self.preedit_len();
+
+        if position < self.cursor_char_idx {
+            Some(position)
+        } else if position < self.cursor_char_idx + preedit_len {
+            None
+        } else {
+            Some(position - preedit_len)
+        }
+    }
+
+    fn preedit_len(&self) -> usize {
+        self.preedit
+            .as_ref()
+            .map_or(0, |preedit| preedit.chars().count())
+    }
+
+    /// Character indices of the value that are currently visible
+    pub fn view_range(&self) -> Range<usize> {
+        self.view_window.clone().into()
+    }
+
+    /// Columns of the view window the selection is drawn in, e.g. for a custom renderer drawing a highlight bar.
+    /// Unlike [`InputState::visible_selection`] these count every cell of a tab and the preedit
+    pub fn selection_columns(&self) -> Option<Range<usize>> {
+        let selection = self.selection()?;
+        let mut columns = self
+            .cell_positions_in_view()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, position)| {
+                self.position_char_idx(*position)
+                    .is_some_and(|char_idx| selection.char_range.contains(&char_idx))
+            })
+            .map(|(column, _)| column);
+
+        let start = columns.next()?;
+        let end = columns.next_back().unwrap_or(start) + 1;
+        Some(start..end)
+    }
+
+    /// Visible part of the selection, relative to the start of the view window
+    pub fn visible_selection(&self) -> Option<Range<usize>> {
+        let selection = self.selection()?;
+        let view_range = self.view_range();
+
+        let start = max(selection.char_range.start, view_range.start);
+        let end = min(selection.char_range.end, view_range.end);
+
+        if start < end {
+            Some(start - view_range.start..end - view_range.start)
+        } else {
+            None
+        }
+    }
+
+    /// Text a default [`crate::Input`] draws for this state in the given width, see [`crate::Input::visible_text`]
+    pub fn visible_text(&self, width: usize) -> String {
+        crate::Input::default().visible_text(self, width)
+    }
+
+    /// Value with the selection marked by `[` and `]`, or without a selection the cursor marked by `|` in front of
+    /// the character it is on, e.g. `žđščć[🎈🎨]👓`. Meant for test assertions and bug reports
+    pub fn debug_render(&self) -> String {
+        match self.selection() {
+            Some(selection) => format!(
+                "{}[{}]{}",
+                self.text_before_selection(),
+                selection.text,
+                self.text_after_selection()
+            ),
+            None => {
+                let cursor_byte_idx = self.cursor_byte_idx();
+                format!(
+                    "{}|{}",
+                    &self.value[..cursor_byte_idx],
+                    &self.value[cursor_byte_idx..]
+                )
+            }
+        }
+    }
+
+    /// Take a copy of the value, cursor, selection and view
+    pub fn snapshot(&self) -> InputSnapshot {
+        InputSnapshot {
+            value: self.value.clone(),
+            cursor_char_idx: self.cursor_char_idx,
+            selection: self.selection().map(|selection| selection.char_range),
+            view_range: self.view_range(),
+        }
+    }
+
+    /// Bring back the value, cursor, selection and view from a snapshot, clamped to fit together
+    pub fn restore(&mut self, snapshot: InputSnapshot) {
+        self.value = snapshot.value;
+        self.cursor_char_idx = snapshot.cursor_char_idx;
+        self.completion = None;
+
+        // The selection always includes the cursor, so it started on the other end of the range
+        self.selection_start_char_idx = snapshot.selection.map(|selection| {
+            if selection.start == self.cursor_char_idx {
+                selection.end.saturating_sub(1)
+            } else {
+                selection.start
+            }
+        });
+        self.view_window = snapshot.view_range.into();
+
+        self.clamp_to_value();
+        self.scroll_to_cursor();
+    }
+
+    /// Text in front of the selection, empty if nothing is selected
+    pub fn text_before_selection(&self) -> &str {
+        match self.selection() {
+            Some(selection) => &self.value[..selection.byte_range.start],
+            None => "",
+        }
+    }
+
+    /// Text behind the selection, empty if nothing is selected
+    pub fn text_after_selection(&self) -> &str {
+        match self.selection() {
+            Some(selection) => &self.value[selection.byte_range.end..],
+            None => "",
+        }
+    }
+
+    /// Currently selected text
+    pub fn selection(&self) -> Option<Selection> {
+        match self.selection_start_char_idx {
+            Some(start_char_idx) => {
+                let min_char_idx = min(start_char_idx, self.cursor_char_idx);
+                let min_byte_idx = char_idx_to_byte_idx(&self.value, min_char_idx);
+                let max_char_idx = max(start_char_idx, self.cursor_char_idx) + 1;
+                let max_byte_idx = char_idx_to_byte_idx(&self.value, max_char_idx);
+
+                Some(Selection {
+                    char_range: min_char_idx..max_char_idx,
+                    byte_range: min_byte_idx..max_byte_idx,
+                    text: self.value[min_byte_idx..max_byte_idx].to_string(),
+                })
+            }
+            None => None,
+        }
+    }
+}
+
+/// Whether handling the message can change the value. Every message is listed, so new ones have to be sorted in
+fn may_edit(msg: &Message) -> bool {
+    match msg {
+        Message::DeleteOnCursor
+        | Message::DeleteBeforeCursor
+        | Message::DeleteWordBeforeCursor
+        | Message::DeleteForwardTo(_)
+        | Message::DeleteBackTo(_)
+        | Message::Char(_)
+        | Message::Paste(_)
+        | Message::Insert(_)
+        | Message::ReplaceChar(_)
+        | Message::PasteFromClipboard
+        | Message::Cut
+        | Message::CompleteNext
+        | Message::CompletePrev => true,
+        // The blur callback gets to fix up the value
+        Message::RemoveFocus => true,
+        Message::Empty
+        | Message::Focus
+        | Message::MoveLeft
+        | Message::MoveLeftWithSelection
+        | Message::MoveRight
+        | Message::MoveRightWithSelection
+        | Message::SelectWordLeft
+        | Message::SelectWordRight
+        | Message::SetCursorAtColumn(_)
+        | Message::SelectToIndex(_)
+        | Message::JumpToEnd
+        | Message::JumpToEndWithSelection
+        | Message::JumpToStart
+        | Message::JumpToStartWithSelection
+        | Message::ToggleInsertMode
+        | Message::ToggleReveal
+        | Message::Copy
+        | Message::SelectAll
+        | Message::ClearSelection => false,
+    }
+}
+
+/// Cells the character takes up, a tab as many as given
+pub(crate) fn cell_width(c: char, tab_width: usize) -> usize {
+    if c == '\t' {
+        tab_width
+    } else {
+        1
+    }
+}
+
+/// Move the offsett right until the cells from it up to and including the cursor fit into the width,
+/// given the cell widths of all characters. A cursor out of view even counting one cell per character is left there
+fn fit_cursor(widths: &[usize], offsett: usize, cursor_position: usize, width: usize) -> usize {
+    if cursor_position < offsett || cursor_position >= offsett + width {
+        return offsett;
+    }
+
+    let mut offsett = offsett;
+    while offsett < cursor_position
+        && widths[offsett..cursor_position].iter().sum::<usize>()
+            + widths.get(cursor_position).copied().unwrap_or(1)
+            > width
+    {
+        offsett += 1;
+    }
+
+    offsett
+}
+
+fn char_idx_to_byte_idx(str: &str, char_idx: usize) -> usize {
+    str.char_indices()
+        .enumerate()
+        .find(|(idx, _)| idx == &char_idx)
         .map(|(_, (idx, _))| idx)
         .unwrap_or(str.len())
 }
 
-/// Selected text inside the [`InputState`]
-#[derive(Debug)]
-pub struct Selection {
-    pub(crate) char_range: Range<usize>,
-    pub(crate) byte_range: Range<usize>,
-    text: String,
-}
+/// Replace every run of whitespace with a single space, or a newline if the run has one and they are kept
+fn collapse_whitespace(str: &str, keep_newlines: bool) -> String {
+    let mut collapsed = String::with_capacity(str.len());
+    let mut run: Option<char> = None;
+
+    for c in str.chars() {
+        if c.is_whitespace() {
+            run = match run {
+                Some('\n') => Some('\n'),
+                _ if keep_newlines && c == '\n' => Some('\n'),
+                _ => Some(' '),
+            };
+        } else {
+            if let Some(space) = run.take() {
+                collapsed.push(space);
+            }
+            collapsed.push(c);
+        }
+    }
+    if let Some(space) = run {
+        collapsed.push(space);
+    }
+
+    collapsed
+}
+
+fn closing_pair(opener: char) -> Option<char> {
+    match opener {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        '"' => Some('"'),
+        '\'' => Some('\''),
+        _ => None,
+    }
+}
+
+fn is_closing_pair(c: char) -> bool {
+    matches!(c, ')' | ']' | '}' | '"' | '\'')
+}
+
+impl From<InputState> for String {
+    fn from(value: InputState) -> Self {
+        value.value
+    }
+}
+
+/// Content of a column of the view window, see [`InputState::hit_test`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hit {
+    /// The character with the given index
+    Char(usize),
+    /// The empty space after the value
+    Padding,
+    /// The cursor, either on a character or right after the value
+    Cursor,
+}
+
+/// What a message did to the value of the [`InputState`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageOutcome {
+    /// The value changed
+    Changed,
+    /// The value stayed the same
+    Unchanged,
+    /// Nothing was deleted because the cursor is at the start (backspace) or the end (delete) of the value,
+    /// e.g. for removing an empty tag from a tag editor
+    AtBoundary,
+}
+
+/// Summary of the [`InputState`] value, passed to the change callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputInfo {
+    /// Number of characters in the value
+    pub len_chars: usize,
+    /// Number of bytes in the value
+    pub len_bytes: usize,
+    /// Whether any text is selected
+    pub has_selection: bool,
+}
+
+/// What [`Message::Copy`] copies when nothing is selected
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CopyFallback {
+    /// The whole value
+    #[default]
+    EntireValue,
+    /// The word the cursor is on or right after
+    CurrentWord,
+    /// Nothing, the clipboard is left as it is
+    Nothing,
+}
+
+/// What [`Message::Copy`] and [`Message::Cut`] put on the clipboard, so a masked input doesn't give away its value
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MaskedCopy {
+    /// The text as it is
+    #[default]
+    Plaintext,
+    /// Nothing, the clipboard is left as it is. Cutting is refused, as the text would be lost
+    Nothing,
+    /// The given symbol for every character of the text
+    Mask(char),
+}
+
+/// Direction in which the selection was made, relative to where it started
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionDirection {
+    /// The cursor is at the end of the selection
+    Forward,
+    /// The cursor is at the start of the selection
+    Backward,
+}
+
+/// Copy of the value, cursor, selection and view of an [`InputState`] at one point in time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputSnapshot {
+    /// Value of the input
+    pub value: String,
+    /// Character index of the cursor
+    pub cursor_char_idx: usize,
+    /// Character range of the selection
+    pub selection: Option<Range<usize>>,
+    /// Character indices that were visible
+    pub view_range: Range<usize>,
+}
+
+/// Selected text inside the [`InputState`]
+#[derive(Debug)]
+pub struct Selection {
+    pub(crate) char_range: Range<usize>,
+    pub(crate) byte_range: Range<usize>,
+    text: String,
+}
+
+impl Selection {
+    /// Number of characters in the selection
+    pub fn char_len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    /// Number of bytes in the selection
+    pub fn byte_len(&self) -> usize {
+        self.byte_range.len()
+    }
+}
+
+impl Deref for Selection {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    #[cfg(feature = "crossterm")]
+    #[test]
+    fn mouse_click() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            view_window: ViewWindow {
+                width: 4,
+                offsett: 2,
+            },
+            ..Default::default()
+        };
+        let area = Rect::new(10, 3, 4, 1);
+        let click = |column, modifiers| MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row: 3,
+            modifiers,
+        };
+
+        state.handle_mouse_event(click(11, KeyModifiers::NONE), area);
+        assert_eq!(state.cursor_char_idx(), 3);
+        assert!(state.selection().is_none());
+
+        // Outside of the input nothing happens
+        state.handle_mouse_event(click(14, KeyModifiers::NONE), area);
+        assert_eq!(state.cursor_char_idx(), 3);
+    }
+
+    #[cfg(feature = "crossterm")]
+    #[test]
+    fn mouse_click_clears_selection() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 3,
+            selection_start_char_idx: Some(2),
+            view_window: ViewWindow {
+                width: 4,
+                offsett: 2,
+            },
+            ..Default::default()
+        };
+        let area = Rect::new(10, 3, 4, 1);
+        let mouse = |kind, column| MouseEvent {
+            kind,
+            column,
+            row: 3,
+            modifiers: KeyModifiers::NONE,
+        };
+
+        state.handle_mouse_event(mouse(MouseEventKind::Down(MouseButton::Left), 13), area);
+        state.handle_mouse_event(mouse(MouseEventKind::Up(MouseButton::Left), 13), area);
+        assert_eq!(state.cursor_char_idx(), 5);
+        assert!(!state.has_selection());
+
+        // Dragging after the click selects
+        state.handle_mouse_event(mouse(MouseEventKind::Down(MouseButton::Left), 11), area);
+        state.handle_mouse_event(mouse(MouseEventKind::Drag(MouseButton::Left), 12), area);
+        state.handle_mouse_event(mouse(MouseEventKind::Drag(MouseButton::Left), 13), area);
+        state.handle_mouse_event(mouse(MouseEventKind::Up(MouseButton::Left), 13), area);
+        assert_eq!(state.selection().unwrap().text, "čć🎈");
+    }
+
+    #[cfg(feature = "crossterm")]
+    #[test]
+    fn mouse_click_past_tab() {
+        // A tab takes up four cells, as drawn by a default widget: `a    bc|`
+        let mut state = InputState::default();
+        state.set_text("a\tbc");
+        state.view_window = ViewWindow {
+            width: 8,
+            offsett: 0,
+        };
+        let area = Rect::new(10, 3, 8, 1);
+        let mouse = |kind, column, modifiers| MouseEvent {
+            kind,
+            column,
+            row: 3,
+            modifiers,
+        };
+
+        state.handle_mouse_event(
+            mouse(
+                MouseEventKind::Down(MouseButton::Left),
+                15,
+                KeyModifiers::NONE,
+            ),
+            area,
+        );
+        assert_eq!(state.cursor_char_idx(), 2);
+
+        // Every cell of the tab belongs to it
+        state.handle_mouse_event(
+            mouse(
+                MouseEventKind::Down(MouseButton::Left),
+                13,
+                KeyModifiers::NONE,
+            ),
+            area,
+        );
+        assert_eq!(state.cursor_char_idx(), 1);
+
+        state.handle_mouse_event(
+            mouse(
+                MouseEventKind::Drag(MouseButton::Left),
+                16,
+                KeyModifiers::NONE,
+            ),
+            area,
+        );
+        assert_eq!(state.selection().unwrap().text, "\tbc");
+    }
+
+    #[test]
+    fn hit_test_past_tab() {
+        let mut state = InputState::default();
+        state.set_text("a\tbc");
+        state.view_window = ViewWindow {
+            width: 8,
+            offsett: 0,
+        };
+
+        assert_eq!(state.hit_test(0), Hit::Char(0));
+        assert_eq!(state.hit_test(3), Hit::Char(1));
+        assert_eq!(state.hit_test(5), Hit::Char(2));
+        assert_eq!(state.hit_test(7), Hit::Cursor);
+        assert_eq!(state.char_at_column(4), Some((1, '\t')));
+        assert_eq!(state.char_at_column(6), Some((3, 'c')));
+        assert_eq!(state.char_at_column(7), None);
+        assert_eq!(state.display_width(), 7);
+
+        state.handle_message(Message::SetCursorAtColumn(6));
+        assert_eq!(state.cursor_char_idx(), 3);
+
+        state.handle_message(Message::MoveLeftWithSelection);
+        state.handle_message(Message::MoveLeftWithSelection);
+        assert_eq!(state.selection_columns(), Some(1..7));
+        assert_eq!(state.visible_selection(), Some(1..4));
+    }
+
+    #[cfg(feature = "crossterm")]
+    #[test]
+    fn mouse_shift_click_extends_selection() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 3,
+            selection_start_char_idx: Some(2),
+            view_window: ViewWindow {
+                width: 4,
+                offsett: 2,
+            },
+            ..Default::default()
+        };
+        let area = Rect::new(10, 3, 4, 1);
+        let shift_click = MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column: 13,
+            row: 3,
+            modifiers: KeyModifiers::SHIFT,
+        };
+
+        assert_eq!(
+            state.mouse_message(shift_click, area),
+            Message::SelectToIndex(5)
+        );
+
+        state.handle_mouse_event(shift_click, area);
+        assert_eq!(state.selection().unwrap().text, "ščć🎈");
+    }
+
+    #[cfg(feature = "crossterm")]
+    #[test]
+    fn handle_event() {
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        let mut state = InputState::default();
+
+        state.handle_event(Event::Key(KeyEvent::new(
+            KeyCode::Char('ž'),
+            KeyModifiers::NONE,
+        )));
+        state.handle_event(Event::Key(KeyEvent::new(
+            KeyCode::Char('🎈'),
+            KeyModifiers::NONE,
+        )));
+        state.handle_event(Event::Key(KeyEvent::new(KeyCode::Left, KeyModifiers::NONE)));
+        state.handle_event(Event::Key(KeyEvent::new(
+            KeyCode::Backspace,
+            KeyModifiers::NONE,
+        )));
+
+        assert_eq!(state.text(), "🎈");
+        assert_eq!(state.cursor_char_idx(), 0);
+    }
+
+    #[cfg(not(feature = "crossterm"))]
+    #[test]
+    fn without_crossterm() {
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Char('ž'));
+        state.handle_message(Message::Paste(String::from("🎈")));
+
+        assert_eq!(state.text(), "ž🎈");
+    }
+
+    #[test]
+    fn empty_message() {
+        let mut state = InputState::default();
+        state.handle_message(Message::Empty);
+        assert_eq!(state, InputState::default());
+    }
+
+    #[test]
+    fn focus() {
+        let mut state = InputState::default();
+        assert!(!state.in_focus);
+
+        state.handle_message(Message::Focus);
+        assert!(state.in_focus);
+
+        state.handle_message(Message::RemoveFocus);
+        assert!(!state.in_focus);
+    }
+
+    #[test]
+    fn select_all_on_focus() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 3,
+            select_all_on_focus: true,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Focus);
+
+        assert!(state.is_focused());
+        assert_eq!(&*state.selection().unwrap(), state.text());
+        assert_eq!(state.cursor_char_idx(), 7);
+
+        state.handle_message(Message::Char('a'));
+        assert_eq!(state.text(), "a");
+    }
+
+    #[test]
+    fn focus_without_select_all() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 3,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Focus);
+
+        assert!(state.is_focused());
+        assert!(state.selection().is_none());
+        assert_eq!(state.cursor_char_idx(), 3);
+    }
+
+    #[test]
+    fn on_blur() {
+        let mut state = InputState {
+            value: String::from("  žđš🎈  "),
+            cursor_char_idx: 8,
+            in_focus: true,
+            ..Default::default()
+        };
+
+        state.set_on_blur(Box::new(|value| *value = value.trim().to_string()));
+        state.handle_message(Message::RemoveFocus);
+
+        assert!(!state.is_focused());
+        assert_eq!(state.text(), "žđš🎈");
+        assert_eq!(state.cursor_char_idx(), 4);
+    }
+
+    #[test]
+    fn set_focus() {
+        let mut state = InputState {
+            value: String::from(" žđš "),
+            ..Default::default()
+        };
+        state.set_on_blur(Box::new(|value| *value = value.trim().to_string()));
+
+        assert!(!state.set_focus(true));
+        assert!(state.is_focused());
+        assert!(state.set_focus(true));
+
+        assert!(state.set_focus(false));
+        assert!(!state.is_focused());
+        assert_eq!(state.text(), "žđš");
+        assert!(!state.set_focus(false));
+    }
+
+    #[test]
+    fn on_change() {
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let mut state = InputState::default();
+
+        let changes_ref = Rc::clone(&changes);
+        state.set_on_change(Box::new(move |value, info| {
+            changes_ref.borrow_mut().push((value.to_string(), info))
+        }));
+
+        state.handle_message(Message::Char('ž'));
+        state.handle_message(Message::MoveLeft);
+        state.handle_message(Message::Paste(String::from("🎈")));
+
+        assert_eq!(
+            *changes.borrow(),
+            vec![
+                (
+                    String::from("ž"),
+                    InputInfo {
+                        len_chars: 1,
+                        len_bytes: 2,
+                        has_selection: false
+                    }
+                ),
+                (
+                    String::from("ž🎈"),
+                    InputInfo {
+                        len_chars: 2,
+                        len_bytes: 6,
+                        has_selection: false
+                    }
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn on_change_debounce() {
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let mut state = InputState::default();
+
+        let changes_ref = Rc::clone(&changes);
+        state.set_on_change(Box::new(move |value, _| {
+            changes_ref.borrow_mut().push(value.to_string())
+        }));
+        state.set_change_debounce(Some(Duration::from_millis(300)));
+
+        let mut now = Instant::now();
+        for c in "žđš".chars() {
+            state.handle_message(Message::Char(c));
+            state.poll_change(now);
+            now += Duration::from_millis(100);
+            state.poll_change(now);
+        }
+        assert!(changes.borrow().is_empty());
+
+        now += Duration::from_millis(200);
+        state.poll_change(now);
+        now += Duration::from_millis(500);
+        state.poll_change(now);
+
+        assert_eq!(*changes.borrow(), vec![String::from("žđš")]);
+    }
+
+    #[test]
+    fn reset_with() {
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let mut state = InputState::default();
+
+        let changes_ref = Rc::clone(&changes);
+        state.set_on_change(Box::new(move |value, _| {
+            changes_ref.borrow_mut().push(value.to_string())
+        }));
+        state.set_change_debounce(Some(Duration::from_millis(300)));
+        state.set_candidates(vec![String::from("foobar")]);
+
+        state.handle_message(Message::Paste(String::from("foo fo")));
+        state.handle_message(Message::CompleteNext);
+        state.handle_message(Message::SelectWordLeft);
+        state.set_highlights(vec![0..3, 4..5]);
+
+        state.reset_with("žđš");
+        assert_eq!(state.text(), "žđš");
+        assert_eq!(state.cursor_char_idx(), 3);
+        assert!(!state.has_selection());
+        assert!(state.highlights().is_empty());
+
+        // The edits of the old value are never reported
+        let mut now = Instant::now();
+        state.poll_change(now);
+        now += Duration::from_millis(500);
+        state.poll_change(now);
+        assert!(changes.borrow().is_empty());
+    }
+
+    #[test]
+    fn navigation_is_not_a_modification() {
+        let mut state = InputState::default();
+        state.set_text("foo žđš");
+        assert!(!state.is_modified());
+
+        for msg in [
+            Message::JumpToStart,
+            Message::JumpToEnd,
+            Message::MoveLeft,
+            Message::MoveRight,
+            Message::MoveLeftWithSelection,
+            Message::MoveRightWithSelection,
+            Message::JumpToStartWithSelection,
+            Message::JumpToEndWithSelection,
+            Message::SelectWordLeft,
+            Message::SelectWordRight,
+            Message::SelectAll,
+            Message::ToggleInsertMode,
+            Message::Focus,
+            Message::RemoveFocus,
+        ] {
+            state.handle_message(msg);
+            assert!(!state.is_modified());
+        }
+
+        state.handle_message(Message::Char('a'));
+        assert!(state.is_modified());
+        state.set_modified(false);
+        state.handle_message(Message::DeleteBeforeCursor);
+        assert!(state.is_modified());
+
+        state.reset_with("baz");
+        assert!(!state.is_modified());
+    }
+
+    #[test]
+    fn echo() {
+        let echoed = Rc::new(RefCell::new(String::new()));
+        let mut state = InputState::default();
+        state.set_max_len(Some(3));
+
+        let echoed_ref = Rc::clone(&echoed);
+        state.set_echo(Some(Box::new(move |c| echoed_ref.borrow_mut().push(c))));
+
+        for c in "ž\nđ🎈a".chars() {
+            state.handle_message(Message::Char(c));
+        }
+        state.handle_message(Message::Paste(String::from("b")));
+
+        assert_eq!(state.text(), "žđ🎈");
+        assert_eq!(*echoed.borrow(), "žđ🎈");
+    }
+
+    #[test]
+    fn on_match() {
+        let matches = Rc::new(RefCell::new(0));
+        let mut state = InputState::default();
+
+        let matches_ref = Rc::clone(&matches);
+        state.set_on_match("/", Box::new(move || *matches_ref.borrow_mut() += 1));
+
+        state.handle_message(Message::Char('/'));
+        state.handle_message(Message::Char('h'));
+        state.handle_message(Message::Char('e'));
+        assert_eq!(*matches.borrow(), 1);
+
+        state.handle_message(Message::JumpToStart);
+        state.handle_message(Message::DeleteOnCursor);
+        state.handle_message(Message::Char('/'));
+        assert_eq!(*matches.borrow(), 2);
+    }
+
+    #[test]
+    fn delete_on_cursor() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 6,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::DeleteOnCursor);
+
+        assert_eq!(state.text(), "žđščć🎈👓");
+        assert_eq!(state.cursor_char_idx(), 6);
+    }
+
+    #[test]
+    fn delete_on_cursor_with_selection() {
+        //žđščć[🎈🎨]👓
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 6,
+            selection_start_char_idx: Some(5),
+            ..Default::default()
+        };
+
+        assert_eq!(&*state.selection().unwrap(), "🎈🎨");
+        state.handle_message(Message::DeleteOnCursor);
+
+        //žđšč[ć]👓
+        assert_eq!(state.text(), "žđščć👓");
+        assert_eq!(state.cursor_char_idx(), 5);
+    }
+
+    #[test]
+    fn selection_len() {
+        //žđšč[ć🎈🎨]👓
+        let state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 6,
+            selection_start_char_idx: Some(4),
+            ..Default::default()
+        };
+
+        let selection = state.selection().unwrap();
+        assert_eq!(selection.char_len(), 3);
+        assert_eq!(selection.byte_len(), 10);
+    }
+
+    #[test]
+    fn cursor_char_byte_range() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 5,
+            ..Default::default()
+        };
+
+        let range = state.cursor_char_byte_range().unwrap();
+        assert_eq!(range, 10..14);
+        assert_eq!(&state.text()[range], "🎈");
+
+        state.cursor_char_idx = 0;
+        assert_eq!(state.cursor_char_byte_range(), Some(0..2));
+
+        state.cursor_char_idx = 8;
+        assert_eq!(state.cursor_char_byte_range(), None);
+    }
+
+    #[test]
+    fn delete_before_cursor() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 6,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::DeleteBeforeCursor);
+
+        assert_eq!(state.text(), "žđščć🎨👓");
+    }
+
+    #[test]
+    fn message_outcome() {
+        let mut state = InputState {
+            value: String::from("žđ"),
+            cursor_char_idx: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            state.handle_message_with_outcome(Message::DeleteBeforeCursor),
+            MessageOutcome::Changed
+        );
+        assert_eq!(
+            state.handle_message_with_outcome(Message::DeleteBeforeCursor),
+            MessageOutcome::AtBoundary
+        );
+        assert_eq!(
+            state.handle_message_with_outcome(Message::MoveRight),
+            MessageOutcome::Unchanged
+        );
+        assert_eq!(
+            state.handle_message_with_outcome(Message::DeleteOnCursor),
+            MessageOutcome::AtBoundary
+        );
+        assert_eq!(state.text(), "đ");
+
+        // Already modified and nobody listening, the change is still reported
+        assert!(state.is_modified());
+        assert_eq!(
+            state.handle_message_with_outcome(Message::Char('š')),
+            MessageOutcome::Changed
+        );
+        assert_eq!(
+            state.handle_message_with_outcome(Message::Paste(String::new())),
+            MessageOutcome::Unchanged
+        );
+    }
+
+    #[test]
+    fn delete_before_cursor_multibyte() {
+        let mut state = InputState {
+            value: String::from("🎈ž👓"),
+            cursor_char_idx: 2,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::DeleteBeforeCursor);
+        assert_eq!(state.text(), "🎈👓");
+        assert_eq!(state.cursor_char_idx(), 1);
+
+        state.handle_message(Message::DeleteBeforeCursor);
+        assert_eq!(state.text(), "👓");
+        assert_eq!(state.cursor_char_idx(), 0);
+
+        state.handle_message(Message::DeleteBeforeCursor);
+        assert_eq!(state.text(), "👓");
+        assert_eq!(state.cursor_char_idx(), 0);
+    }
+
+    #[test]
+    fn delete_before_cursor_with_selection() {
+        //žđšč[ć🎈🎨]👓
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 6,
+            selection_start_char_idx: Some(4),
+            ..Default::default()
+        };
+
+        assert_eq!(&*state.selection().unwrap(), "ć🎈🎨");
+
+        state.handle_message(Message::DeleteBeforeCursor);
+
+        assert_eq!(state.text(), "žđšč👓");
+        assert_eq!(state.cursor_char_idx, 4);
+    }
+
+    #[test]
+    fn move_left() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 7,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::MoveLeft);
+        assert_eq!(state.cursor_char_idx, 6);
+
+        state.handle_message(Message::MoveLeft);
+        assert_eq!(state.cursor_char_idx, 5);
+    }
+
+    #[test]
+    fn move_left_cancles_selection() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 7,
+            selection_start_char_idx: Some(5),
+            ..Default::default()
+        };
+
+        state.handle_message(Message::MoveLeft);
+        assert!(state.selection().is_none());
+    }
+
+    #[test]
+    fn move_left_on_start() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            ..Default::default()
+        };
+
+        state.handle_message(Message::MoveLeft);
+
+        assert_eq!(state.cursor_char_idx(), 0);
+    }
+
+    #[test]
+    fn move_left_with_selection() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 6,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::MoveLeftWithSelection);
+
+        assert_eq!(&*state.selection().unwrap(), "🎈🎨");
+        assert_eq!(state.cursor_char_idx(), 5);
+    }
+
+    #[test]
+    fn move_right() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            ..Default::default()
+        };
+
+        state.handle_message(Message::MoveRight);
+        assert_eq!(state.cursor_char_idx(), 1);
+
+        state.handle_message(Message::MoveRight);
+        assert_eq!(state.cursor_char_idx(), 2);
+    }
+
+    #[test]
+    fn moving_right_cancles_selection() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            selection_start_char_idx: Some(5),
+            ..Default::default()
+        };
+
+        state.handle_message(Message::MoveRight);
+        assert!(state.selection().is_none());
+    }
+
+    #[test]
+    fn move_right_on_end() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 8,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::MoveRight);
+
+        assert_eq!(state.cursor_char_idx(), 8);
+    }
+
+    #[test]
+    fn move_right_with_selecion() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            ..Default::default()
+        };
+
+        state.handle_message(Message::MoveRightWithSelection);
+
+        assert_eq!(&*state.selection().unwrap(), "žđ");
+        assert_eq!(state.cursor_char_idx(), 1);
+    }
+
+    #[test]
+    fn move_cursor_by_delta() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 2,
+            selection_start_char_idx: Some(0),
+            ..Default::default()
+        };
+
+        state.move_cursor(3);
+        assert_eq!(state.cursor_char_idx(), 5);
+        assert!(state.selection().is_none());
+        assert!(state.view_window.contains(5));
+
+        state.move_cursor(-4);
+        assert_eq!(state.cursor_char_idx(), 1);
+        assert!(state.view_window.contains(1));
+    }
+
+    #[test]
+    fn move_cursor_out_of_range() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 2,
+            ..Default::default()
+        };
+
+        state.move_cursor(100);
+        assert_eq!(state.cursor_char_idx(), 8);
+
+        state.move_cursor(-100);
+        assert_eq!(state.cursor_char_idx(), 0);
+
+        state.move_cursor(isize::MIN);
+        assert_eq!(state.cursor_char_idx(), 0);
+    }
+
+    #[test]
+    fn insert_flag_emoji() {
+        // 🇸🇮 is made of two regional indicator characters
+        let mut state = InputState {
+            value: String::from("ab"),
+            cursor_char_idx: 1,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Insert(String::from("🇸🇮")));
+        assert_eq!(state.text(), "a🇸🇮b");
+        assert_eq!(state.cursor_char_idx(), 3);
+
+        state.handle_message(Message::MoveLeftWithSelection);
+        state.handle_message(Message::MoveLeftWithSelection);
+        state.handle_message(Message::Insert(String::from("🇭🇷")));
+        assert_eq!(state.text(), "a🇭🇷");
+        assert_eq!(state.cursor_char_idx(), 3);
+        assert!(state.selection().is_none());
+    }
+
+    #[test]
+    fn insert_in_insert_mode() {
+        let mut state = InputState {
+            value: String::from("žđš"),
+            cursor_char_idx: 1,
+            insert_mode: true,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Insert(String::from("🇸🇮")));
+        assert_eq!(state.text(), "ž🇸🇮");
+        assert_eq!(state.cursor_char_idx(), 3);
+
+        state.handle_message(Message::Insert(String::from("a\nb")));
+        assert_eq!(state.text(), "ž🇸🇮ab");
+    }
+
+    #[test]
+    fn selection_direction() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 4,
+            ..Default::default()
+        };
+        assert_eq!(state.selection_direction(), None);
+
+        state.handle_message(Message::MoveRightWithSelection);
+        state.handle_message(Message::MoveRightWithSelection);
+        assert_eq!(
+            state.selection_direction(),
+            Some(SelectionDirection::Forward)
+        );
+
+        state.handle_message(Message::MoveLeft);
+        state.handle_message(Message::MoveLeftWithSelection);
+        state.handle_message(Message::MoveLeftWithSelection);
+        assert_eq!(
+            state.selection_direction(),
+            Some(SelectionDirection::Backward)
+        );
+    }
+
+    #[test]
+    fn jump_to_end() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            ..Default::default()
+        };
+
+        state.handle_message(Message::JumpToEnd);
+
+        assert_eq!(state.cursor_byte_idx(), state.text().len());
+    }
+
+    #[test]
+    fn jump_to_end_collapses_selection() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 3,
+            selection_start_char_idx: Some(1),
+            ..Default::default()
+        };
+
+        state.handle_message(Message::JumpToEnd);
+
+        assert!(state.selection().is_none());
+        assert_eq!(state.cursor_char_idx(), 8);
+    }
+
+    #[test]
+    fn jump_to_end_keeps_selection() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 3,
+            selection_start_char_idx: Some(1),
+            collapse_selection_on_jump: false,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::JumpToEnd);
+
+        assert_eq!(&*state.selection().unwrap(), "đščć🎈🎨👓");
+        assert_eq!(state.cursor_char_idx(), 8);
+    }
+
+    #[test]
+    fn jump_to_end_with_selection() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            ..Default::default()
+        };
+
+        state.handle_message(Message::JumpToEndWithSelection);
+
+        assert_eq!(&*state.selection().unwrap(), state.text());
+        assert_eq!(state.cursor_char_idx(), 7);
+    }
+
+    #[test]
+    fn jump_to_start() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 7,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::JumpToStart);
+
+        assert_eq!(state.cursor_char_idx(), 0);
+    }
+
+    #[test]
+    fn jump_to_start_collapses_selection() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 6,
+            selection_start_char_idx: Some(4),
+            ..Default::default()
+        };
+
+        state.handle_message(Message::JumpToStart);
+
+        assert!(state.selection().is_none());
+        assert_eq!(state.cursor_char_idx(), 0);
+    }
+
+    #[test]
+    fn jump_to_start_keeps_selection() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 6,
+            selection_start_char_idx: Some(4),
+            collapse_selection_on_jump: false,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::JumpToStart);
+
+        assert_eq!(&*state.selection().unwrap(), "žđščć");
+        assert_eq!(state.cursor_char_idx(), 0);
+    }
+
+    #[test]
+    fn jump_to_start_with_selection() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 6,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::JumpToStartWithSelection);
+
+        assert_eq!(state.cursor_char_idx(), 0);
+        assert_eq!(&*state.selection().unwrap(), "žđščć🎈🎨");
+    }
+
+    #[test]
+    fn character_input_at_end() {
+        let mut state = InputState {
+            value: String::new(),
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Char('0'));
+        assert_eq!(state.text(), "0");
+        assert_eq!(state.cursor_byte_idx(), 1);
+
+        state.handle_message(Message::Char('1'));
+        assert_eq!(state.text(), "01");
+        assert_eq!(state.cursor_byte_idx(), 2);
+    }
+
+    #[test]
+    fn character_input_in_middle() {
+        let mut state = InputState {
+            value: String::from("foo bar"),
+            cursor_char_idx: 3,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Char(' '));
+        state.handle_message(Message::Char('😎'));
+
+        assert_eq!(state.text(), "foo 😎 bar");
+        assert_eq!(state.cursor_char_idx(), 5);
+    }
+
+    #[test]
+    fn character_input_at_end_with_insert_mode() {
+        let mut state = InputState {
+            value: String::new(),
+            insert_mode: true,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Char('0'));
+        assert_eq!(state.text(), "0");
+        assert_eq!(state.cursor_byte_idx(), 1);
+
+        state.handle_message(Message::Char('1'));
+        assert_eq!(state.text(), "01");
+        assert_eq!(state.cursor_byte_idx(), 2);
+    }
+
+    #[test]
+    fn character_input_in_insert_mode() {
+        let mut state = InputState {
+            value: String::from("foo bar"),
+            insert_mode: true,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Char('h'));
+        state.handle_message(Message::Char('e'));
+        state.handle_message(Message::Char('l'));
+        state.handle_message(Message::Char('l'));
+        state.handle_message(Message::Char('o'));
+        state.handle_message(Message::Char(' '));
+        state.handle_message(Message::Char('w'));
+        state.handle_message(Message::Char('o'));
+        state.handle_message(Message::Char('r'));
+        state.handle_message(Message::Char('l'));
+        state.handle_message(Message::Char('d'));
+
+        assert_eq!(state.text(), "hello world");
+        assert_eq!(state.cursor_char_idx(), 11);
+    }
+
+    #[test]
+    fn newline_is_ignored() {
+        let mut state = InputState {
+            value: String::from("foo"),
+            cursor_char_idx: 3,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Char('\n'));
+
+        assert_eq!(state.text(), "foo");
+        assert_eq!(state.cursor_char_idx(), 3);
+    }
+
+    #[test]
+    fn character_input_on_selection() {
+        let mut state = InputState {
+            value: String::from("foo bar"),
+            cursor_char_idx: 5,
+            selection_start_char_idx: Some(1),
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Char('a'));
+
+        assert_eq!(state.text(), "far");
+        assert_eq!(state.cursor_char_idx(), 2);
+    }
+
+    #[test]
+    fn character_input_at_end_in_insert_mode() {
+        let mut state = InputState {
+            value: String::new(),
+            insert_mode: true,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Char('0'));
+        assert_eq!(state.text(), "0");
+        assert_eq!(state.cursor_byte_idx(), 1);
+
+        state.handle_message(Message::Char('1'));
+        assert_eq!(state.text(), "01");
+        assert_eq!(state.cursor_byte_idx(), 2);
+    }
+
+    #[test]
+    fn paste_at_end() {
+        let mut state = InputState {
+            value: String::from("foo"),
+            cursor_char_idx: 3,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Paste(String::from(" bar")));
+
+        assert_eq!(state.text(), "foo bar");
+        assert_eq!(state.cursor_char_idx(), 7);
+    }
+
+    #[test]
+    fn paste_in_middle() {
+        let mut state = InputState {
+            value: String::from("foo bar"),
+            cursor_char_idx: 3,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Paste(String::from("baz ")));
+
+        assert_eq!(state.text(), "foo baz bar");
+        assert_eq!(state.cursor_char_idx(), 7);
+    }
+
+    #[test]
+    fn paste_overwrite() {
+        let mut state = InputState {
+            value: String::from("abcde"),
+            cursor_char_idx: 2,
+            insert_mode: true,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Paste(String::from("XY")));
+        assert_eq!(state.text(), "abXYe");
+        assert_eq!(state.cursor_char_idx(), 4);
+
+        // What doesn't fit over the remaining characters is appended
+        state.handle_message(Message::Paste(String::from("žđš")));
+        assert_eq!(state.text(), "abXYžđš");
+        assert_eq!(state.cursor_char_idx(), 7);
+    }
+
+    #[test]
+    fn paste_on_selection() {
+        let mut state = InputState {
+            value: String::from("foo bar"),
+            cursor_char_idx: 4,
+            selection_start_char_idx: Some(2),
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Paste(String::from("faz")));
+
+        assert_eq!(state.text(), "fofazar");
+        assert_eq!(state.cursor_char_idx(), 5);
+        assert!(state.selection().is_none());
+    }
+
+    #[test]
+    fn insert_mode_toggle() {
+        let mut state = InputState::default();
+
+        assert!(!state.insert_mode);
+
+        state.handle_message(Message::ToggleInsertMode);
+
+        assert!(state.insert_mode);
+    }
+
+    #[test]
+    fn auto_pairs_insert() {
+        let mut state = InputState {
+            auto_pairs: true,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Char('('));
+        assert_eq!(state.text(), "()");
+        assert_eq!(state.cursor_char_idx(), 1);
+
+        state.handle_message(Message::Char('"'));
+        assert_eq!(state.text(), "(\"\")");
+        assert_eq!(state.cursor_char_idx(), 2);
+    }
+
+    #[test]
+    fn auto_pairs_skip_over() {
+        let mut state = InputState {
+            auto_pairs: true,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::Char('['));
+        state.handle_message(Message::Char('🎈'));
+        state.handle_message(Message::Char(']'));
+
+        assert_eq!(state.text(), "[🎈]");
+        assert_eq!(state.cursor_char_idx(), 3);
+    }
+
+    #[test]
+    fn auto_pairs_delete_pair() {
+        let mut state = InputState {
+            value: String::from("ž{}"),
+            cursor_char_idx: 2,
+            auto_pairs: true,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::DeleteBeforeCursor);
+
+        assert_eq!(state.text(), "ž");
+        assert_eq!(state.cursor_char_idx(), 1);
+    }
+
+    #[test]
+    fn auto_pairs_disabled() {
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Char('('));
+
+        assert_eq!(state.text(), "(");
+    }
+
+    #[test]
+    fn find_all() {
+        let state = InputState {
+            value: String::from("Žđš🎈žĐŠ🎈žđš"),
+            ..Default::default()
+        };
+
+        assert_eq!(state.find_all("žđš", true), vec![0..3, 4..7, 8..11]);
+        assert_eq!(state.find_all("žđš", false), vec![8..11]);
+        assert!(state.find_all("", true).is_empty());
+    }
+
+    #[test]
+    fn find_all_does_not_overlap() {
+        let state = InputState {
+            value: String::from("🎈🎈🎈🎈🎈"),
+            ..Default::default()
+        };
+
+        assert_eq!(state.find_all("🎈🎈", false), vec![0..2, 2..4]);
+    }
+
+    #[test]
+    fn replace_all() {
+        let mut state = InputState {
+            value: String::from("🎈ž🎈ž🎈đ"),
+            cursor_char_idx: 5,
+            ..Default::default()
+        };
+
+        assert_eq!(state.replace_all("🎈", "ab"), 3);
+        assert_eq!(state.text(), "abžabžabđ");
+        // Still on đ
+        assert_eq!(state.cursor_char_idx(), 8);
+
+        assert_eq!(state.replace_all("", "x"), 0);
+        assert_eq!(state.replace_all("q", "x"), 0);
+        assert_eq!(state.text(), "abžabžabđ");
+    }
+
+    #[test]
+    fn replace_all_cursor_inside_match() {
+        let mut state = InputState {
+            value: String::from("žđš žđš"),
+            cursor_char_idx: 5,
+            ..Default::default()
+        };
+
+        assert_eq!(state.replace_all("žđš", "🎈"), 2);
+        assert_eq!(state.text(), "🎈 🎈");
+        assert_eq!(state.cursor_char_idx(), 2);
+    }
+
+    #[test]
+    fn commit_preedit() {
+        let mut state = InputState {
+            value: String::from("ab"),
+            cursor_char_idx: 1,
+            ..Default::default()
+        };
+
+        state.set_preedit(Some(String::from("にほ")));
+        assert_eq!(state.text(), "ab");
+        assert_eq!(state.preedit(), Some("にほ"));
+
+        state.commit_preedit();
+        assert_eq!(state.text(), "aにほb");
+        assert_eq!(state.cursor_char_idx(), 3);
+        assert!(state.preedit().is_none());
+    }
+
+    #[test]
+    fn set_text() {
+        let mut state = InputState::default();
+
+        state.set_text("žđščć🎈🎨👓");
+
+        assert_eq!(state.text(), "žđščć🎈🎨👓");
+        assert_eq!(state.cursor_char_idx(), 8);
+        assert!(state.view_window.contains(state.cursor_char_idx()));
+    }
+
+    #[test]
+    fn into_string() {
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Char('ž'));
+        state.handle_message(Message::Paste(String::from("🎈a")));
+
+        assert_eq!(String::from(state), "ž🎈a");
+    }
+
+    #[test]
+    fn set_text_keep_cursor() {
+        let mut state = InputState {
+            value: String::from("žđš"),
+            cursor_char_idx: 2,
+            ..Default::default()
+        };
+
+        state.set_text_keep_cursor("🎈🎨👓🐈🐨");
+        assert_eq!(state.text(), "🎈🎨👓🐈🐨");
+        assert_eq!(state.cursor_char_idx(), 2);
+
+        state.set_text_keep_cursor("a");
+        assert_eq!(state.text(), "a");
+        assert_eq!(state.cursor_char_idx(), 1);
+    }
+
+    #[test]
+    fn multiline_newline() {
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Char('a'));
+        state.handle_message(Message::Char('\n'));
+        assert_eq!(state.text(), "a");
+
+        state.set_multiline(true);
+        state.handle_message(Message::Char('\n'));
+        state.handle_message(Message::Char('b'));
+        assert_eq!(state.text(), "a\nb");
+        assert_eq!(state.cursor_char_idx(), 3);
+
+        state.set_text("c\r\nd");
+        assert_eq!(state.text(), "c\nd");
+    }
+
+    #[cfg(feature = "crossterm")]
+    #[test]
+    fn multiline_enter() {
+        use crossterm::event::KeyEvent;
+
+        let mut state = InputState::default();
+        state.set_multiline(true);
+        state.handle_message(Message::Focus);
+
+        state.handle_event(Event::Key(KeyEvent::new(
+            KeyCode::Enter,
+            KeyModifiers::NONE,
+        )));
+
+        assert_eq!(state.text(), "\n");
+        assert!(state.is_focused());
+    }
+
+    #[test]
+    fn copy_fallback() {
+        let mut state = InputState::default();
+        state.set_text("foo žđš baz");
+        state.handle_message(Message::JumpToStart);
+        for _ in 0..5 {
+            state.handle_message(Message::MoveRight);
+        }
+
+        assert_eq!(state.copy_text().as_deref(), Some("foo žđš baz"));
+
+        state.set_copy_fallback(CopyFallback::CurrentWord);
+        assert_eq!(state.copy_text().as_deref(), Some("žđš"));
+
+        state.set_copy_fallback(CopyFallback::Nothing);
+        assert_eq!(state.copy_text(), None);
+
+        // A selection is always copied
+        state.handle_message(Message::MoveRightWithSelection);
+        assert_eq!(state.copy_text().as_deref(), Some("đš"));
+    }
+
+    #[test]
+    fn password_copy() {
+        let mut state = InputState::password();
+        state.set_text("hunter2");
+        assert_eq!(state.copy_text(), None);
+
+        state.set_masked_copy(MaskedCopy::Mask('•'));
+        assert_eq!(state.copy_text().as_deref(), Some("•••••••"));
+
+        state.set_masked_copy(MaskedCopy::Plaintext);
+        assert_eq!(state.copy_text().as_deref(), Some("hunter2"));
+    }
+
+    #[derive(Default)]
+    struct MockClipboard(Rc<RefCell<Option<String>>>);
+
+    impl Clipboard for MockClipboard {
+        fn get(&mut self) -> Option<String> {
+            self.0.borrow().clone()
+        }
+
+        fn set(&mut self, text: String) {
+            *self.0.borrow_mut() = Some(text);
+        }
+    }
+
+    #[test]
+    fn clipboard_cut_paste() {
+        let contents = Rc::new(RefCell::new(None));
+        let mut state = InputState::default();
+        state.set_clipboard(Box::new(MockClipboard(Rc::clone(&contents))));
+        state.set_text("foo žđš");
+
+        state.handle_message(Message::SelectWordLeft);
+        state.handle_message(Message::Cut);
+        assert_eq!(state.text(), "foo ");
+        assert_eq!(contents.borrow().as_deref(), Some("žđš"));
+
+        state.handle_message(Message::JumpToStart);
+        state.handle_message(Message::PasteFromClipboard);
+        assert_eq!(state.text(), "fžđšoo ");
+    }
+
+    #[test]
+    fn clipboard_cut_whole_value() {
+        let contents = Rc::new(RefCell::new(None));
+        let mut state = InputState::default();
+        state.set_clipboard(Box::new(MockClipboard(Rc::clone(&contents))));
+        state.set_text("žđš foo");
+
+        state.handle_message(Message::Cut);
+        assert_eq!(state.text(), "");
+        assert_eq!(contents.borrow().as_deref(), Some("žđš foo"));
+        assert!(state.is_modified());
+    }
+
+    #[cfg(not(feature = "clipboard"))]
+    #[test]
+    fn no_clipboard() {
+        let mut state = InputState::default();
+        state.set_text("žđš");
+        state.handle_message(Message::SelectWordLeft);
+
+        state.handle_message(Message::Copy);
+        state.handle_message(Message::Cut);
+        state.handle_message(Message::PasteFromClipboard);
+        assert_eq!(state.debug_render(), "[žđš]");
+        assert!(!state.is_modified());
+    }
+
+    #[test]
+    fn password_cut() {
+        let mut state = InputState::password();
+        state.set_clipboard(Box::<MockClipboard>::default());
+        state.set_text("hunter2");
+
+        state.handle_message(Message::Cut);
+        assert_eq!(state.text(), "hunter2");
+
+        // The mask goes on the clipboard, the value is cut as usual
+        state.set_masked_copy(MaskedCopy::Mask('•'));
+        state.handle_message(Message::Cut);
+        assert_eq!(state.text(), "");
+    }
+
+    #[test]
+    fn copy_current_word_off_word() {
+        let mut state = InputState::default();
+        state.set_copy_fallback(CopyFallback::CurrentWord);
+        state.set_text("foo  bar");
+        state.handle_message(Message::JumpToStart);
+        for _ in 0..4 {
+            state.handle_message(Message::MoveRight);
+        }
+
+        assert_eq!(state.copy_text(), None);
+    }
+
+    #[test]
+    fn map_selection() {
+        let mut state = InputState::default();
+        state.set_text("foo žđš");
+        assert!(!state.map_selection(|str| str.to_uppercase()));
+
+        state.handle_message(Message::SelectWordLeft);
+        assert!(state.map_selection(|str| str.to_uppercase()));
+        assert_eq!(state.text(), "foo ŽĐŠ");
+        assert_eq!(state.cursor_char_idx(), 7);
+        assert!(!state.has_selection());
+    }
+
+    #[test]
+    fn map_selection_changes_length() {
+        let mut state = InputState::default();
+        state.set_text("a b");
+        state.handle_message(Message::JumpToStart);
+        state.handle_message(Message::MoveRightWithSelection);
+
+        assert!(state.map_selection(|str| str.replace(' ', "%20")));
+        assert_eq!(state.text(), "a%20b");
+        assert_eq!(state.cursor_char_idx(), 4);
+    }
+
+    #[test]
+    fn follow_tail() {
+        let mut state = InputState {
+            view_window: ViewWindow {
+                width: 5,
+                offsett: 0,
+            },
+            ..Default::default()
+        };
+        state.set_follow_tail(true);
+
+        state.handle_message(Message::Paste(String::from("abc")));
+        state.handle_message(Message::JumpToStart);
+        for _ in 0..6 {
+            state.handle_message(Message::Char('x'));
+        }
+        assert_eq!(state.cursor_char_idx(), 6);
+        assert_eq!(state.view_range(), 5..10);
+        assert_eq!(state.visible_text(5), "xabc ");
+
+        // Scrolled away from the tail, the window follows the cursor
+        state.handle_message(Message::JumpToStart);
+        state.handle_message(Message::Char('y'));
+        assert_eq!(state.view_range(), 0..5);
+    }
+
+    #[test]
+    fn follow_tail_keeps_cursor_in_view() {
+        let mut state = InputState {
+            view_window: ViewWindow {
+                width: 4,
+                offsett: 0,
+            },
+            ..Default::default()
+        };
+        state.set_follow_tail(true);
+        state.set_auto_pairs(true);
 
-impl Deref for Selection {
-    type Target = str;
+        // The closers pile up after the cursor, so the tail gets away from it
+        for _ in 0..6 {
+            state.handle_message(Message::Char('('));
+            assert!(state.view_window.contains(state.cursor_char_idx()));
+        }
 
-    fn deref(&self) -> &Self::Target {
-        &self.text
+        for _ in 0..3 {
+            state.handle_message(Message::Char('x'));
+            assert!(state.view_window.contains(state.cursor_char_idx()));
+        }
+        assert_eq!(state.text(), "((((((xxx))))))");
+        assert_eq!(state.cursor_char_idx(), 9);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn scroll_animation_step() {
+        let mut state = InputState {
+            view_window: ViewWindow {
+                width: 10,
+                offsett: 0,
+            },
+            ..Default::default()
+        };
+        state.set_scroll_animation_step(8);
+
+        state.handle_message(Message::Paste("žđš🎈".repeat(5)));
+        assert_eq!(state.cursor_char_idx(), 20);
+        // Scrolled by two steps, so the cursor has room to the right
+        assert_eq!(state.view_range(), 16..26);
+
+        for _ in 0..6 {
+            state.handle_message(Message::Char('a'));
+        }
+        assert_eq!(state.view_range(), 24..34);
+
+        // A step wider than the window still keeps the cursor in view
+        state.set_scroll_animation_step(100);
+        state.handle_message(Message::Paste("ab".repeat(10)));
+        assert_eq!(state.cursor_char_idx(), 46);
+        assert_eq!(state.view_range(), 46..56);
+    }
 
     #[test]
-    fn empty_message() {
+    fn collapse_whitespace_on_paste() {
         let mut state = InputState::default();
-        state.handle_message(Message::Empty);
-        assert_eq!(state, InputState::default());
+        state.set_collapse_whitespace_on_paste(true);
+
+        state.handle_message(Message::Paste(String::from("a    b\t\tc")));
+        assert_eq!(state.text(), "a b c");
+        assert_eq!(state.cursor_char_idx(), 5);
+
+        state.set_text("");
+        state.handle_message(Message::Paste(String::from(" a \r\n\n b ")));
+        assert_eq!(state.text(), " a b ");
+
+        state.set_text("");
+        state.set_multiline(true);
+        state.handle_message(Message::Paste(String::from("a \r\n\n b  c")));
+        assert_eq!(state.text(), "a\nb c");
     }
 
     #[test]
-    fn focus() {
+    fn reject_control_chars() {
         let mut state = InputState::default();
-        assert!(!state.in_focus);
 
-        state.handle_message(Message::Focus);
-        assert!(state.in_focus);
+        state.handle_message(Message::Char('\u{7}'));
+        assert_eq!(state.text(), "\u{7}");
 
-        state.handle_message(Message::RemoveFocus);
-        assert!(!state.in_focus);
+        state.set_text("");
+        state.set_reject_control_chars(true);
+        state.handle_message(Message::Char('\u{7}'));
+        state.handle_message(Message::Char('a'));
+        state.handle_message(Message::Char('\t'));
+        assert_eq!(state.text(), "a\t");
+        assert_eq!(state.cursor_char_idx(), 2);
+
+        state.handle_message(Message::Char('\n'));
+        assert_eq!(state.text(), "a\t");
+        state.set_multiline(true);
+        state.handle_message(Message::Char('\n'));
+        assert_eq!(state.text(), "a\t\n");
     }
 
     #[test]
-    fn delete_on_cursor() {
+    fn line_count() {
+        let mut state = InputState::default();
+        assert_eq!(state.line_count(), 1);
+
+        state.set_multiline(true);
+        state.set_text("ž\n\n🎈");
+        assert_eq!(state.line_count(), 3);
+    }
+
+    #[test]
+    fn current_line() {
+        let mut state = InputState::default();
+        state.set_text("žđš");
+        assert_eq!(state.current_line(), "žđš");
+
+        state.set_multiline(true);
+        state.set_text("foo\nžđš🎈\nbaz");
+        state.handle_message(Message::JumpToStart);
+        for _ in 0..6 {
+            state.handle_message(Message::MoveRight);
+        }
+        assert_eq!(state.current_line(), "žđš🎈");
+
+        // On the newline that ends it
+        for _ in 0..2 {
+            state.handle_message(Message::MoveRight);
+        }
+        assert_eq!(state.current_line(), "žđš🎈");
+    }
+
+    #[test]
+    fn set_text_drops_line_breaks() {
+        let mut state = InputState::default();
+
+        state.set_text("a\r\nb\rž\n");
+
+        assert_eq!(state.text(), "abž");
+        assert_eq!(state.cursor_char_idx(), 3);
+    }
+
+    #[test]
+    fn set_text_shorter_than_selection() {
         let mut state = InputState {
             value: String::from("žđščć🎈🎨👓"),
-            cursor_char_idx: 6,
+            cursor_char_idx: 7,
+            selection_start_char_idx: Some(5),
             ..Default::default()
         };
 
-        state.handle_message(Message::DeleteOnCursor);
+        state.set_text("🎈");
 
-        assert_eq!(state.text(), "žđščć🎈👓");
-        assert_eq!(state.cursor_char_idx(), 6);
+        assert!(state.selection().is_none());
+        assert_eq!(state.text(), "🎈");
+        assert_eq!(state.cursor_char_idx(), 1);
     }
 
     #[test]
-    fn delete_on_cursor_with_selection() {
-        //žđščć[🎈🎨]👓
+    fn select_word_right() {
         let mut state = InputState {
-            value: String::from("žđščć🎈🎨👓"),
-            cursor_char_idx: 6,
-            selection_start_char_idx: Some(5),
+            value: String::from("foo, žđš 🎈"),
             ..Default::default()
         };
 
-        assert_eq!(&*state.selection().unwrap(), "🎈🎨");
-        state.handle_message(Message::DeleteOnCursor);
+        state.handle_message(Message::SelectWordRight);
+        assert_eq!(state.selection().unwrap().text, "foo");
 
-        //žđšč[ć]👓
-        assert_eq!(state.text(), "žđščć👓");
+        state.handle_message(Message::SelectWordRight);
+        assert_eq!(state.selection().unwrap().text, "foo, žđš");
+
+        state.handle_message(Message::SelectWordRight);
+        assert_eq!(state.selection().unwrap().text, "foo, žđš 🎈");
+        assert_eq!(state.cursor_char_idx(), 9);
+    }
+
+    #[test]
+    fn select_word_left() {
+        let mut state = InputState {
+            value: String::from("foo, žđš bar"),
+            cursor_char_idx: 12,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::SelectWordLeft);
+        assert_eq!(state.selection().unwrap().text, "bar");
+
+        state.handle_message(Message::SelectWordLeft);
+        assert_eq!(state.selection().unwrap().text, "žđš bar");
+
+        state.handle_message(Message::SelectWordLeft);
+        assert_eq!(state.selection().unwrap().text, "foo, žđš bar");
+        assert_eq!(state.cursor_char_idx(), 0);
+    }
+
+    #[test]
+    fn select_word_keeps_anchor() {
+        let mut state = InputState {
+            value: String::from("foo bar baz"),
+            cursor_char_idx: 5,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::MoveRightWithSelection);
+        state.handle_message(Message::SelectWordRight);
+        assert_eq!(state.selection().unwrap().text, "ar baz");
+
+        state.handle_message(Message::SelectWordLeft);
+        assert_eq!(state.selection().unwrap().text, "ar b");
+
+        state.handle_message(Message::SelectWordLeft);
+        state.handle_message(Message::SelectWordLeft);
+        assert_eq!(state.selection().unwrap().text, "foo ba");
+    }
+
+    #[test]
+    fn text_around_selection() {
+        let mut state = InputState {
+            value: String::from("žđšćć🐈🐨👓"),
+            cursor_char_idx: 2,
+            ..Default::default()
+        };
+        assert_eq!(state.text_before_selection(), "");
+        assert_eq!(state.text_after_selection(), "");
+
+        state.handle_message(Message::MoveRightWithSelection);
+        state.handle_message(Message::MoveRightWithSelection);
+        state.handle_message(Message::MoveRightWithSelection);
+        assert_eq!(state.selection().unwrap().text, "šćć🐈");
+        assert_eq!(state.text_before_selection(), "žđ");
+        assert_eq!(state.text_after_selection(), "🐨👓");
+    }
+
+    #[test]
+    fn normalize_nfc() {
+        let mut state = InputState::default();
+        state.set_normalization(Some(Normalization::Nfc));
+
+        state.handle_message(Message::Char('e'));
+        state.handle_message(Message::Char('\u{301}'));
+        assert_eq!(state.text(), "é");
+        assert_eq!(state.cursor_char_idx(), 1);
+
+        state.handle_message(Message::Paste(String::from("z\u{30C}a")));
+        assert_eq!(state.text(), "éža");
+        assert_eq!(state.cursor_char_idx(), 3);
+    }
+
+    #[test]
+    fn normalize_nfd() {
+        let mut state = InputState::default();
+        state.set_normalization(Some(Normalization::Nfd));
+
+        state.handle_message(Message::Insert(String::from("ža")));
+        assert_eq!(state.text(), "z\u{30C}a");
+        assert_eq!(state.cursor_char_idx(), 3);
+    }
+
+    #[test]
+    fn scroll_ratio() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓🐈🐨"),
+            view_window: ViewWindow {
+                width: 4,
+                offsett: 0,
+            },
+            ..Default::default()
+        };
+        assert_eq!(state.scroll_ratio(), (0.0, 0.4));
+
+        state.view_window.offsett = 3;
+        assert_eq!(state.scroll_ratio(), (0.3, 0.4));
+
+        state.view_window.offsett = 6;
+        assert_eq!(state.scroll_ratio(), (0.6, 0.4));
+
+        state.set_text("");
+        assert_eq!(state.scroll_ratio(), (0.0, 1.0));
+    }
+
+    #[test]
+    fn select_to_index_forward() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 1,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::SelectToIndex(5));
+        assert_eq!(state.selection().unwrap().text, "đščć🎈");
         assert_eq!(state.cursor_char_idx(), 5);
+
+        // Past the end the cursor stops on the last character
+        state.handle_message(Message::SelectToIndex(100));
+        assert_eq!(state.selection().unwrap().text, "đščć🎈🎨👓");
+        assert_eq!(state.cursor_char_idx(), 7);
     }
 
     #[test]
-    fn delete_before_cursor() {
+    fn select_to_index_backward() {
         let mut state = InputState {
             value: String::from("žđščć🎈🎨👓"),
-            cursor_char_idx: 6,
+            cursor_char_idx: 8,
             ..Default::default()
         };
 
-        state.handle_message(Message::DeleteBeforeCursor);
+        state.handle_message(Message::SelectToIndex(2));
+        assert_eq!(state.selection().unwrap().text, "ščć🎈🎨👓");
+        assert_eq!(
+            state.selection_direction(),
+            Some(SelectionDirection::Backward)
+        );
+    }
 
-        assert_eq!(state.text(), "žđščć🎨👓");
+    #[test]
+    fn visible_selection() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 4,
+            selection_start_char_idx: Some(3),
+            view_window: ViewWindow {
+                width: 4,
+                offsett: 2,
+            },
+            ..Default::default()
+        };
+        assert_eq!(state.visible_selection(), Some(1..3));
+
+        // Clipped on the left
+        state.selection_start_char_idx = Some(0);
+        assert_eq!(state.visible_selection(), Some(0..3));
+
+        // Clipped on the right
+        state.cursor_char_idx = 7;
+        state.selection_start_char_idx = Some(4);
+        assert_eq!(state.visible_selection(), Some(2..4));
+
+        // Entirely out of view
+        state.view_window.offsett = 0;
+        state.selection_start_char_idx = Some(5);
+        assert_eq!(state.visible_selection(), None);
     }
 
     #[test]
-    fn delete_before_cursor_with_selection() {
-        //žđšč[ć🎈🎨]👓
+    fn selection_columns() {
         let mut state = InputState {
             value: String::from("žđščć🎈🎨👓"),
-            cursor_char_idx: 6,
-            selection_start_char_idx: Some(4),
+            cursor_char_idx: 4,
+            selection_start_char_idx: Some(3),
+            view_window: ViewWindow {
+                width: 4,
+                offsett: 2,
+            },
             ..Default::default()
         };
+        assert_eq!(state.selection_columns(), Some(1..3));
 
-        assert_eq!(&*state.selection().unwrap(), "ć🎈🎨");
+        // Clipped on both sides
+        state.cursor_char_idx = 7;
+        state.selection_start_char_idx = Some(0);
+        assert_eq!(state.selection_columns(), Some(0..4));
+    }
+
+    #[test]
+    fn max_len() {
+        let mut state = InputState::default();
+        state.set_max_len(Some(4));
+
+        state.handle_message(Message::Paste(String::from("žđš🎈")));
+        state.handle_message(Message::Char('a'));
+        assert_eq!(state.text(), "žđš🎈");
+        assert_eq!(state.cursor_char_idx(), 4);
 
         state.handle_message(Message::DeleteBeforeCursor);
+        state.handle_message(Message::Paste(String::from("ab")));
+        assert_eq!(state.text(), "žđš");
+    }
 
-        assert_eq!(state.text(), "žđšč👓");
-        assert_eq!(state.cursor_char_idx, 4);
+    #[test]
+    fn max_len_type_over_selection() {
+        let mut state = InputState::default();
+        state.set_max_len(Some(5));
+        state.handle_message(Message::Paste(String::from("žđščć")));
+
+        state.handle_message(Message::JumpToStart);
+        state.handle_message(Message::MoveRightWithSelection);
+        state.handle_message(Message::MoveRightWithSelection);
+        state.handle_message(Message::Char('a'));
+
+        assert_eq!(state.text(), "ačć");
+        assert_eq!(state.cursor_char_idx(), 1);
     }
 
     #[test]
-    fn move_left() {
+    fn scroll_right_to_cursor_left_of_window() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 2,
+            view_window: ViewWindow {
+                width: 3,
+                offsett: 4,
+            },
+            ..Default::default()
+        };
+        state.set_scroll_animation_step(4);
+
+        state.scroll_right_to_cursor();
+        assert_eq!(state.view_window.offsett, 2);
+    }
+
+    #[test]
+    fn scroll_to_cursor() {
         let mut state = InputState {
             value: String::from("žđščć🎈🎨👓"),
+            view_window: ViewWindow {
+                width: 3,
+                offsett: 0,
+            },
+            ..Default::default()
+        };
+
+        state.cursor_char_idx = 6;
+        state.scroll_to_cursor();
+        assert_eq!(state.view_window.offsett, 4);
+
+        state.cursor_char_idx = 1;
+        state.scroll_to_cursor();
+        assert_eq!(state.view_window.offsett, 1);
+
+        // Already visible, so nothing moves
+        state.cursor_char_idx = 3;
+        state.scroll_to_cursor();
+        assert_eq!(state.view_window.offsett, 1);
+    }
+
+    #[test]
+    fn delete_forward_to() {
+        let mut state = InputState {
+            value: String::from("žđš🎈ćč🎈ab"),
+            cursor_char_idx: 1,
+            ..Default::default()
+        };
+
+        state.handle_message(Message::DeleteForwardTo('🎈'));
+        assert_eq!(state.text(), "žćč🎈ab");
+        assert_eq!(state.cursor_char_idx(), 1);
+
+        state.handle_message(Message::DeleteForwardTo('x'));
+        assert_eq!(state.text(), "žćč🎈ab");
+    }
+
+    #[test]
+    fn delete_back_to() {
+        let mut state = InputState {
+            value: String::from("ž🎈đš🎈ćčab"),
             cursor_char_idx: 7,
+            view_window: ViewWindow {
+                width: 2,
+                offsett: 6,
+            },
+            ..Default::default()
+        };
+
+        state.handle_message(Message::DeleteBackTo('🎈'));
+        assert_eq!(state.text(), "ž🎈đšab");
+        assert_eq!(state.cursor_char_idx(), 4);
+        assert!(state.view_window.contains(state.cursor_char_idx()));
+
+        state.handle_message(Message::DeleteBackTo('x'));
+        assert_eq!(state.text(), "ž🎈đšab");
+    }
+
+    #[test]
+    fn debug_render() {
+        let mut state = InputState {
+            value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 6,
+            selection_start_char_idx: Some(5),
             ..Default::default()
         };
+        assert_eq!(state.debug_render(), "žđščć[🎈🎨]👓");
+
+        state.handle_message(Message::MoveLeft);
+        assert_eq!(state.debug_render(), "žđščć|🎈🎨👓");
+
+        state.handle_message(Message::JumpToEnd);
+        assert_eq!(state.debug_render(), "žđščć🎈🎨👓|");
+    }
+
+    #[test]
+    fn restore_selection_after_deleting_it() {
+        for msg in [Message::DeleteOnCursor, Message::DeleteBeforeCursor] {
+            let mut state = InputState::default();
+            state.set_text("foo žđš");
+            state.handle_message(Message::SelectWordLeft);
+            let snapshot = state.snapshot();
+
+            state.handle_message(msg);
+            assert_eq!(state.text(), "foo ");
+            assert!(!state.has_selection());
+
+            state.restore(snapshot);
+            assert_eq!(state.text(), "foo žđš");
+            assert_eq!(state.selection().unwrap().to_string(), "žđš");
+            assert_eq!(state.cursor_char_idx(), 4);
+            assert_eq!(
+                state.selection_direction(),
+                Some(SelectionDirection::Backward)
+            );
+        }
 
-        state.handle_message(Message::MoveLeft);
-        assert_eq!(state.cursor_char_idx, 6);
+        // Selected up to the end, with the cursor past the last character
+        let mut state = InputState::default();
+        state.set_text("foo žđš");
+        state.handle_message(Message::JumpToStart);
+        state.handle_message(Message::JumpToEndWithSelection);
+        let snapshot = state.snapshot();
 
-        state.handle_message(Message::MoveLeft);
-        assert_eq!(state.cursor_char_idx, 5);
+        state.handle_message(Message::DeleteBeforeCursor);
+        assert_eq!(state.text(), "");
+
+        state.restore(snapshot.clone());
+        assert_eq!(state.snapshot(), snapshot);
+        assert_eq!(state.selection().unwrap().to_string(), "foo žđš");
     }
 
     #[test]
-    fn move_left_cancles_selection() {
+    fn snapshot_round_trip() {
         let mut state = InputState {
             value: String::from("žđščć🎈🎨👓"),
-            cursor_char_idx: 7,
+            cursor_char_idx: 2,
             selection_start_char_idx: Some(5),
+            view_window: ViewWindow {
+                width: 4,
+                offsett: 1,
+            },
             ..Default::default()
         };
 
-        state.handle_message(Message::MoveLeft);
-        assert!(state.selection().is_none());
+        let snapshot = state.snapshot();
+        assert_eq!(snapshot.selection, Some(2..6));
+        assert_eq!(snapshot.view_range, 1..5);
+
+        state.handle_message(Message::DeleteOnCursor);
+        state.handle_message(Message::Paste(String::from("ab")));
+        assert_ne!(state.snapshot(), snapshot);
+
+        state.restore(snapshot.clone());
+        assert_eq!(state.snapshot(), snapshot);
+        assert_eq!(state.text(), "žđščć🎈🎨👓");
+        assert_eq!(
+            state.selection_direction(),
+            Some(SelectionDirection::Backward)
+        );
     }
 
     #[test]
-    fn move_left_on_start() {
+    fn delete_word_before_cursor() {
         let mut state = InputState {
-            value: String::from("žđščć🎈🎨👓"),
+            value: String::from("foo, žđš 🎈"),
+            cursor_char_idx: 9,
             ..Default::default()
         };
 
-        state.handle_message(Message::MoveLeft);
+        state.handle_message(Message::DeleteWordBeforeCursor);
+        assert_eq!(state.text(), "foo, 🎈");
+        assert_eq!(state.cursor_char_idx(), 5);
 
+        state.handle_message(Message::DeleteWordBeforeCursor);
+        assert_eq!(state.text(), "🎈");
         assert_eq!(state.cursor_char_idx(), 0);
+
+        state.handle_message(Message::DeleteWordBeforeCursor);
+        assert_eq!(state.text(), "🎈");
     }
 
     #[test]
-    fn move_left_with_selection() {
-        let mut state = InputState {
-            value: String::from("žđščć🎈🎨👓"),
-            cursor_char_idx: 6,
-            ..Default::default()
-        };
+    fn jump_to_end_with_selection_empty() {
+        let mut state = InputState::default();
 
-        state.handle_message(Message::MoveLeftWithSelection);
+        state.handle_message(Message::JumpToEndWithSelection);
 
-        assert_eq!(&*state.selection().unwrap(), "🎈🎨");
-        assert_eq!(state.cursor_char_idx(), 5);
+        assert!(state.selection().is_none());
+        assert_eq!(state.cursor_char_idx(), 0);
     }
 
     #[test]
-    fn move_right() {
+    fn jump_to_end_with_selection_single_char() {
         let mut state = InputState {
-            value: String::from("žđščć🎈🎨👓"),
+            value: String::from("🎈"),
             ..Default::default()
         };
 
-        state.handle_message(Message::MoveRight);
-        assert_eq!(state.cursor_char_idx(), 1);
+        state.handle_message(Message::JumpToEndWithSelection);
+        assert_eq!(state.selection().unwrap().text, "🎈");
+        assert_eq!(state.cursor_char_idx(), 0);
 
         state.handle_message(Message::MoveRight);
-        assert_eq!(state.cursor_char_idx(), 2);
+        state.handle_message(Message::JumpToEndWithSelection);
+        assert!(state.selection().is_none());
+        assert_eq!(state.cursor_char_idx(), 1);
     }
 
     #[test]
-    fn moving_right_cancles_selection() {
-        let mut state = InputState {
+    fn hit_test() {
+        let state = InputState {
             value: String::from("žđščć🎈🎨👓"),
-            selection_start_char_idx: Some(5),
+            cursor_char_idx: 5,
+            view_window: ViewWindow {
+                width: 6,
+                offsett: 4,
+            },
             ..Default::default()
         };
 
-        state.handle_message(Message::MoveRight);
-        assert!(state.selection().is_none());
+        assert_eq!(state.hit_test(0), Hit::Char(4));
+        assert_eq!(state.hit_test(1), Hit::Cursor);
+        assert_eq!(state.hit_test(3), Hit::Char(7));
+        assert_eq!(state.hit_test(4), Hit::Padding);
+        assert_eq!(state.hit_test(5), Hit::Padding);
     }
 
     #[test]
-    fn move_right_on_end() {
+    fn cursor_report() {
         let mut state = InputState {
             value: String::from("žđščć🎈🎨👓"),
-            cursor_char_idx: 8,
+            cursor_char_idx: 6,
+            view_window: ViewWindow {
+                width: 4,
+                offsett: 4,
+            },
             ..Default::default()
         };
 
-        state.handle_message(Message::MoveRight);
+        assert_eq!(
+            state.cursor_report(Rect::new(10, 2, 4, 1)).as_deref(),
+            Some("\x1b[3;13H")
+        );
 
-        assert_eq!(state.cursor_char_idx(), 8);
+        // Clipped by the area
+        assert_eq!(state.cursor_report(Rect::new(10, 2, 2, 1)), None);
+
+        state.view_window.offsett = 0;
+        assert_eq!(state.cursor_report(Rect::new(10, 2, 4, 1)), None);
     }
 
     #[test]
-    fn move_right_with_selecion() {
+    fn char_at_column() {
         let mut state = InputState {
             value: String::from("žđščć🎈🎨👓"),
+            cursor_char_idx: 8,
+            view_window: ViewWindow {
+                width: 4,
+                offsett: 5,
+            },
             ..Default::default()
         };
 
-        state.handle_message(Message::MoveRightWithSelection);
+        assert_eq!(state.char_at_column(0), Some((5, '🎈')));
+        assert_eq!(state.char_at_column(2), Some((7, '👓')));
+        // Padding after the value and columns outside the window
+        assert_eq!(state.char_at_column(3), None);
+        assert_eq!(state.char_at_column(4), None);
 
-        assert_eq!(&*state.selection().unwrap(), "žđ");
-        assert_eq!(state.cursor_char_idx(), 1);
+        state.view_window.offsett = 1;
+        assert_eq!(state.char_at_column(0), Some((1, 'đ')));
+        assert_eq!(state.char_at_column(3), Some((4, 'ć')));
+        assert_eq!(state.char_at_column(4), None);
     }
 
     #[test]
-    fn jump_to_end() {
-        let mut state = InputState {
-            value: String::from("žđščć🎈🎨👓"),
-            ..Default::default()
-        };
+    fn collapse_selection_to_edge() {
+        let mut state = InputState::default();
+        state.set_collapse_selection_to_edge(true);
+        state.set_text("foo žđš baz");
+        state.handle_message(Message::JumpToStart);
+        for _ in 0..4 {
+            state.handle_message(Message::MoveRight);
+        }
+        for _ in 0..2 {
+            state.handle_message(Message::MoveRightWithSelection);
+        }
+        assert_eq!(state.debug_render(), "foo [žđš] baz");
 
-        state.handle_message(Message::JumpToEnd);
+        state.handle_message(Message::MoveLeft);
+        assert_eq!(state.cursor_char_idx(), 4);
+        assert!(!state.has_selection());
 
-        assert_eq!(state.cursor_byte_idx(), state.text().len());
+        for _ in 0..2 {
+            state.handle_message(Message::MoveRightWithSelection);
+        }
+        state.handle_message(Message::MoveRight);
+        assert_eq!(state.cursor_char_idx(), 7);
+        assert!(!state.has_selection());
+
+        // Without a selection the cursor moves as usual
+        state.handle_message(Message::MoveRight);
+        assert_eq!(state.cursor_char_idx(), 8);
     }
 
     #[test]
-    fn jump_to_end_with_selection() {
-        let mut state = InputState {
-            value: String::from("žđščć🎈🎨👓"),
-            ..Default::default()
-        };
-
-        state.handle_message(Message::JumpToEndWithSelection);
+    fn collapse_selection_to_edge_at_end() {
+        let mut state = InputState::default();
+        state.set_collapse_selection_to_edge(true);
+        state.set_collapse_selection_on_jump(false);
+        state.set_text("foo bar");
+        state.handle_message(Message::JumpToStart);
+        state.handle_message(Message::MoveRightWithSelection);
 
-        assert_eq!(&*state.selection().unwrap(), state.text());
+        // The selection reaches up to the cursor past the last character
+        state.handle_message(Message::JumpToEnd);
+        assert!(state.has_selection());
+        state.handle_message(Message::MoveRight);
         assert_eq!(state.cursor_char_idx(), 7);
+        assert!(!state.has_selection());
     }
 
     #[test]
-    fn jump_to_start() {
+    fn select_all_then_shrink() {
         let mut state = InputState {
-            value: String::from("žđščć🎈🎨👓"),
-            cursor_char_idx: 7,
+            value: String::from("žđš🎈"),
+            cursor_char_idx: 1,
             ..Default::default()
         };
 
-        state.handle_message(Message::JumpToStart);
+        state.handle_message(Message::SelectAll);
+        assert_eq!(state.debug_render(), "[žđš🎈]");
 
-        assert_eq!(state.cursor_char_idx(), 0);
+        // The selection is anchored at the start, so it shrinks from the right end
+        state.handle_message(Message::MoveLeftWithSelection);
+        assert_eq!(state.debug_render(), "[žđš]🎈");
+        state.handle_message(Message::MoveLeftWithSelection);
+        assert_eq!(state.debug_render(), "[žđ]š🎈");
+
+        // Moving right again grows it back, but not past the end
+        state.handle_message(Message::MoveRightWithSelection);
+        state.handle_message(Message::MoveRightWithSelection);
+        state.handle_message(Message::MoveRightWithSelection);
+        assert_eq!(state.debug_render(), "[žđš🎈]");
     }
 
     #[test]
-    fn jump_to_start_with_selection() {
+    fn select_all_then_extend_left() {
         let mut state = InputState {
-            value: String::from("žđščć🎈🎨👓"),
-            cursor_char_idx: 6,
+            value: String::from("žđš"),
             ..Default::default()
         };
 
+        state.handle_message(Message::SelectAll);
         state.handle_message(Message::JumpToStartWithSelection);
-
+        assert_eq!(state.debug_render(), "[ž]đš");
         assert_eq!(state.cursor_char_idx(), 0);
-        assert_eq!(&*state.selection().unwrap(), "žđščć🎈🎨");
     }
 
     #[test]
-    fn character_input_at_end() {
-        let mut state = InputState {
-            value: String::new(),
-            ..Default::default()
-        };
+    fn display_width() {
+        let mut state = InputState::default();
+        assert_eq!(state.display_width(), 0);
 
-        state.handle_message(Message::Char('0'));
-        assert_eq!(state.text(), "0");
-        assert_eq!(state.cursor_byte_idx(), 1);
+        state.set_text("žđš🎈中文");
+        assert_eq!(state.display_width(), state.text().chars().count());
 
-        state.handle_message(Message::Char('1'));
-        assert_eq!(state.text(), "01");
-        assert_eq!(state.cursor_byte_idx(), 2);
+        // A tab takes up as many cells as the default tab width
+        state.set_text("a\tb");
+        assert_eq!(state.display_width(), 6);
     }
 
     #[test]
-    fn character_input_in_middle() {
-        let mut state = InputState {
-            value: String::from("foo bar"),
-            cursor_char_idx: 3,
-            ..Default::default()
-        };
+    fn complete_cycles_candidates() {
+        let mut state = InputState::default();
+        state.set_candidates(vec![String::from("foobar"), String::from("foobaz")]);
+        state.set_text("cd fo");
 
-        state.handle_message(Message::Char(' '));
-        state.handle_message(Message::Char('😎'));
+        state.handle_message(Message::CompleteNext);
+        assert_eq!(state.text(), "cd foobar");
+        assert_eq!(state.cursor_char_idx(), 9);
+        state.handle_message(Message::CompleteNext);
+        assert_eq!(state.text(), "cd foobaz");
 
-        assert_eq!(state.text(), "foo 😎 bar");
+        // Past the last candidate the typed prefix comes back
+        state.handle_message(Message::CompleteNext);
+        assert_eq!(state.text(), "cd fo");
         assert_eq!(state.cursor_char_idx(), 5);
+        state.handle_message(Message::CompleteNext);
+        assert_eq!(state.text(), "cd foobar");
+
+        // And backwards
+        state.handle_message(Message::CompletePrev);
+        assert_eq!(state.text(), "cd fo");
+        state.handle_message(Message::CompletePrev);
+        assert_eq!(state.text(), "cd foobaz");
+        state.handle_message(Message::CompletePrev);
+        assert_eq!(state.text(), "cd foobar");
+
+        // Typing accepts the candidate, the next completion replaces the new word
+        state.handle_message(Message::Char(' '));
+        state.handle_message(Message::CompletePrev);
+        assert_eq!(state.text(), "cd foobar foobaz");
     }
 
     #[test]
-    fn character_input_at_end_with_insert_mode() {
-        let mut state = InputState {
-            value: String::new(),
-            insert_mode: true,
-            ..Default::default()
-        };
-
-        state.handle_message(Message::Char('0'));
-        assert_eq!(state.text(), "0");
-        assert_eq!(state.cursor_byte_idx(), 1);
+    fn set_text_ends_completion() {
+        let mut state = InputState::default();
+        state.set_candidates(vec![String::from("foobar"), String::from("foobaz")]);
+        state.set_text("cd fo");
+        state.handle_message(Message::CompleteNext);
+        assert_eq!(state.text(), "cd foobar");
 
-        state.handle_message(Message::Char('1'));
-        assert_eq!(state.text(), "01");
-        assert_eq!(state.cursor_byte_idx(), 2);
+        // The completion started on the old value, the next one starts over on the new one
+        state.set_text("ab");
+        state.handle_message(Message::CompletePrev);
+        assert_eq!(state.text(), "foobaz");
+        assert_eq!(state.cursor_char_idx(), 6);
+        state.handle_message(Message::CompletePrev);
+        assert_eq!(state.text(), "foobar");
+        state.handle_message(Message::CompletePrev);
+        assert_eq!(state.text(), "ab");
+        assert_eq!(state.cursor_char_idx(), 2);
     }
 
+    #[cfg(feature = "crossterm")]
     #[test]
-    fn character_input_in_insert_mode() {
-        let mut state = InputState {
-            value: String::from("foo bar"),
-            insert_mode: true,
-            ..Default::default()
-        };
+    fn tab_completes() {
+        use crossterm::event::KeyEvent;
 
-        state.handle_message(Message::Char('h'));
-        state.handle_message(Message::Char('e'));
-        state.handle_message(Message::Char('l'));
-        state.handle_message(Message::Char('l'));
-        state.handle_message(Message::Char('o'));
-        state.handle_message(Message::Char(' '));
-        state.handle_message(Message::Char('w'));
-        state.handle_message(Message::Char('o'));
-        state.handle_message(Message::Char('r'));
-        state.handle_message(Message::Char('l'));
-        state.handle_message(Message::Char('d'));
+        let mut state = InputState::default();
+        state.set_text("fo");
 
-        assert_eq!(state.text(), "hello world");
-        assert_eq!(state.cursor_char_idx(), 11);
+        // Without candidates a tab is typed
+        state.handle_event(Event::Key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE)));
+        assert_eq!(state.text(), "fo\t");
+
+        state.set_text("fo");
+        state.set_candidates(vec![String::from("foo"), String::from("for")]);
+        state.handle_event(Event::Key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE)));
+        assert_eq!(state.text(), "foo");
+        state.handle_event(Event::Key(KeyEvent::new(
+            KeyCode::BackTab,
+            KeyModifiers::SHIFT,
+        )));
+        assert_eq!(state.text(), "fo");
     }
 
     #[test]
-    fn character_input_on_selection() {
-        let mut state = InputState {
-            value: String::from("foo bar"),
-            cursor_char_idx: 5,
-            selection_start_char_idx: Some(1),
-            ..Default::default()
-        };
-
-        state.handle_message(Message::Char('a'));
+    fn complete_without_candidates() {
+        let mut state = InputState::default();
+        state.set_text("fo");
 
-        assert_eq!(state.text(), "far");
+        state.handle_message(Message::CompleteNext);
+        assert_eq!(state.text(), "fo");
         assert_eq!(state.cursor_char_idx(), 2);
     }
 
     #[test]
-    fn character_input_at_end_in_insert_mode() {
-        let mut state = InputState {
-            value: String::new(),
-            insert_mode: true,
-            ..Default::default()
-        };
+    fn clear_selection() {
+        let mut state = InputState::default();
+        state.set_text("žđš foo");
+        state.handle_message(Message::SelectWordLeft);
+        let cursor_char_idx = state.cursor_char_idx();
 
-        state.handle_message(Message::Char('0'));
-        assert_eq!(state.text(), "0");
-        assert_eq!(state.cursor_byte_idx(), 1);
+        state.handle_message(Message::ClearSelection);
+        assert!(state.selection().is_none());
+        assert_eq!(state.text(), "žđš foo");
+        assert_eq!(state.cursor_char_idx(), cursor_char_idx);
+        assert!(!state.is_modified());
+    }
 
-        state.handle_message(Message::Char('1'));
-        assert_eq!(state.text(), "01");
-        assert_eq!(state.cursor_byte_idx(), 2);
+    #[test]
+    fn common_prefix_len() {
+        let mut state = InputState::default();
+        state.set_text("žđš🎈");
+
+        assert_eq!(state.common_prefix_len("žđš🎈🎨"), 4);
+        assert_eq!(state.common_prefix_len("žđć"), 2);
+        assert_eq!(state.common_prefix_len("ž"), 1);
+        assert_eq!(state.common_prefix_len("đš"), 0);
+        assert_eq!(state.common_prefix_len(""), 0);
+
+        // Same first byte, but a different character
+        assert_eq!(state.common_prefix_len("ż"), 0);
     }
 
     #[test]
-    fn paste_at_end() {
-        let mut state = InputState {
-            value: String::from("foo"),
-            cursor_char_idx: 3,
-            ..Default::default()
-        };
+    fn replace_char() {
+        let mut state = InputState::default();
+        state.set_text("žđš");
+        state.handle_message(Message::JumpToStart);
+        state.handle_message(Message::MoveRight);
 
-        state.handle_message(Message::Paste(String::from(" bar")));
+        state.handle_message(Message::ReplaceChar('🎈'));
+        assert_eq!(state.text(), "ž🎈š");
+        assert_eq!(state.cursor_char_idx(), 1);
 
-        assert_eq!(state.text(), "foo bar");
-        assert_eq!(state.cursor_char_idx(), 7);
+        state.handle_message(Message::ReplaceChar('a'));
+        assert_eq!(state.text(), "žaš");
+        assert_eq!(state.cursor_char_idx(), 1);
+
+        // Nothing to replace at the end
+        state.handle_message(Message::JumpToEnd);
+        state.handle_message(Message::ReplaceChar('b'));
+        assert_eq!(state.text(), "žaš");
+        assert_eq!(state.cursor_char_idx(), 3);
     }
 
     #[test]
-    fn paste_in_middle() {
-        let mut state = InputState {
-            value: String::from("foo bar"),
-            cursor_char_idx: 3,
-            ..Default::default()
-        };
+    fn words() {
+        let mut state = InputState::default();
+        state.set_text("foo  bar baz");
 
-        state.handle_message(Message::Paste(String::from("baz ")));
+        assert_eq!(
+            state.words().collect::<Vec<_>>(),
+            vec![(0..3, "foo"), (5..8, "bar"), (9..12, "baz")]
+        );
 
-        assert_eq!(state.text(), "foo baz bar");
-        assert_eq!(state.cursor_char_idx(), 7);
+        state.set_text(" žđ-🎈 ");
+        assert_eq!(state.words().collect::<Vec<_>>(), vec![(1..3, "žđ")]);
+        state.set_word_boundary(WordBoundary::Whitespace);
+        assert_eq!(state.words().collect::<Vec<_>>(), vec![(1..5, "žđ-🎈")]);
     }
 
     #[test]
-    fn paste_on_selection() {
+    fn at_word_boundary() {
         let mut state = InputState {
-            value: String::from("foo bar"),
-            cursor_char_idx: 4,
-            selection_start_char_idx: Some(2),
+            value: String::from("žđš ćč-🎈"),
             ..Default::default()
         };
 
-        state.handle_message(Message::Paste(String::from("faz")));
+        // Start
+        assert!(state.at_word_boundary());
 
-        assert_eq!(state.text(), "fofazar");
-        assert_eq!(state.cursor_char_idx(), 5);
-        assert!(state.selection().is_none());
+        // Mid-word
+        state.cursor_char_idx = 1;
+        assert!(!state.at_word_boundary());
+
+        // After space
+        state.cursor_char_idx = 4;
+        assert!(state.at_word_boundary());
+
+        // Punctuation
+        state.cursor_char_idx = 6;
+        assert!(state.at_word_boundary());
+        state.set_word_boundary(WordBoundary::Whitespace);
+        assert!(!state.at_word_boundary());
+
+        // End
+        state.cursor_char_idx = 8;
+        assert!(state.at_word_boundary());
     }
 
     #[test]
-    fn insert_mode_toggle() {
-        let mut state = InputState::default();
+    fn random_messages_keep_invariants() {
+        // Xorshift, so the sequence is the same on every run without pulling in a dependency
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move |bound: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % bound as u64) as usize
+        };
+        let chars = ['a', 'ž', '🎈', ' ', '(', ')'];
 
-        assert!(!state.insert_mode);
+        for _ in 0..500 {
+            let mut state = InputState {
+                auto_pairs: next(2) == 0,
+                view_window: ViewWindow {
+                    width: 1 + next(8),
+                    offsett: 0,
+                },
+                ..Default::default()
+            };
 
-        state.handle_message(Message::ToggleInsertMode);
+            for _ in 0..100 {
+                let msg = match next(27) {
+                    0 => Message::Empty,
+                    1 => Message::Focus,
+                    2 => Message::RemoveFocus,
+                    3 => Message::DeleteOnCursor,
+                    4 => Message::DeleteBeforeCursor,
+                    5 => Message::MoveLeft,
+                    6 => Message::MoveLeftWithSelection,
+                    7 => Message::MoveRight,
+                    8 => Message::MoveRightWithSelection,
+                    9 => Message::JumpToEnd,
+                    10 => Message::JumpToEndWithSelection,
+                    11 => Message::JumpToStart,
+                    12 => Message::JumpToStartWithSelection,
+                    13 => Message::ToggleInsertMode,
+                    14 => Message::Paste((0..next(4)).map(|_| chars[next(chars.len())]).collect()),
+                    18 => Message::SelectToIndex(next(12)),
+                    19 => Message::DeleteForwardTo(chars[next(chars.len())]),
+                    20 => Message::DeleteBackTo(chars[next(chars.len())]),
+                    21 => Message::DeleteWordBeforeCursor,
+                    22 => Message::SetCursorAtColumn(next(8)),
+                    23 => Message::SelectAll,
+                    24 => Message::ReplaceChar(chars[next(chars.len())]),
+                    25 => Message::ClearSelection,
+                    16 => Message::SelectWordLeft,
+                    17 => Message::SelectWordRight,
+                    15 => Message::Insert((0..next(4)).map(|_| chars[next(chars.len())]).collect()),
+                    _ => Message::Char(chars[next(chars.len())]),
+                };
+                // Messages that can't edit skip the comparison, so they must really leave the value alone
+                let old_value = (!may_edit(&msg)).then(|| state.text().to_string());
 
-        assert!(state.insert_mode);
+                state.handle_message(msg);
+                state.check_invariants();
+                let _ = state.selection();
+                if let Some(old_value) = old_value {
+                    assert_eq!(state.text(), old_value);
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,163 @@
+use crate::{InputState, Message};
+
+/// Mode of the [`VimState`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum VimMode {
+    /// Keys are commands, e.g. `h`/`l` move the cursor and `x` deletes
+    #[default]
+    Normal,
+    /// Keys are inserted as text, like in a regular input
+    Insert,
+}
+
+/// Modal vi-style layer on top of an [`InputState`]
+///
+/// In normal mode `h`/`l` move, `0`/`$` jump to the start/end, `x` deletes, `r` replaces a character, `dd` clears the input
+/// and `i`/`a` enter insert mode. [`Message::RemoveFocus`] (`Esc`) returns from insert mode to normal mode,
+/// in normal mode it removes the focus as usual
+#[derive(Debug, Default)]
+pub struct VimState {
+    mode: VimMode,
+    pending_delete: bool,
+    pending_replace: bool,
+}
+
+impl VimState {
+    /// Current mode
+    pub fn mode(&self) -> VimMode {
+        self.mode
+    }
+
+    /// Update the [`InputState`] with the given message, interpreted according to the current mode
+    pub fn handle_message(&mut self, state: &mut InputState, msg: Message) {
+        match self.mode {
+            VimMode::Insert => match msg {
+                Message::RemoveFocus => {
+                    // Like vi, the cursor steps back onto the last inserted character
+                    self.mode = VimMode::Normal;
+                    state.handle_message(Message::MoveLeft);
+                }
+                msg => state.handle_message(msg),
+            },
+            VimMode::Normal => {
+                let pending_delete = self.pending_delete;
+                self.pending_delete = false;
+                let pending_replace = self.pending_replace;
+                self.pending_replace = false;
+
+                match msg {
+                    Message::Char(c) if pending_replace => {
+                        state.handle_message(Message::ReplaceChar(c))
+                    }
+                    Message::Char('h') => state.handle_message(Message::MoveLeft),
+                    Message::Char('l') => state.handle_message(Message::MoveRight),
+                    Message::Char('0') => state.handle_message(Message::JumpToStart),
+                    Message::Char('$') => state.handle_message(Message::JumpToEnd),
+                    Message::Char('x') => state.handle_message(Message::DeleteOnCursor),
+                    Message::Char('r') => self.pending_replace = true,
+                    Message::Char('i') => self.mode = VimMode::Insert,
+                    Message::Char('a') => {
+                        state.handle_message(Message::MoveRight);
+                        self.mode = VimMode::Insert;
+                    }
+                    Message::Char('d') => {
+                        if pending_delete {
+                            // Through messages, so it counts as an edit like any other
+                            state.handle_message(Message::SelectAll);
+                            state.handle_message(Message::DeleteOnCursor);
+                        } else {
+                            self.pending_delete = true;
+                        }
+                    }
+                    Message::Char(_)
+                    | Message::Paste(_)
+                    | Message::Insert(_)
+                    | Message::PasteFromClipboard => {
+                        // Text is only inserted in insert mode
+                    }
+                    msg => state.handle_message(msg),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_keys(vim: &mut VimState, state: &mut InputState, keys: &str) {
+        for c in keys.chars() {
+            vim.handle_message(state, Message::Char(c));
+        }
+    }
+
+    #[test]
+    fn insert_then_delete() {
+        let mut vim = VimState::default();
+        let mut state = InputState::default();
+
+        type_keys(&mut vim, &mut state, "iabc");
+        assert_eq!(vim.mode(), VimMode::Insert);
+
+        vim.handle_message(&mut state, Message::RemoveFocus);
+        assert_eq!(vim.mode(), VimMode::Normal);
+
+        type_keys(&mut vim, &mut state, "hhx");
+        assert_eq!(state.text(), "bc");
+        assert_eq!(state.cursor_char_idx(), 0);
+    }
+
+    #[test]
+    fn append() {
+        let mut vim = VimState::default();
+        let mut state = InputState::default();
+
+        type_keys(&mut vim, &mut state, "iac");
+        vim.handle_message(&mut state, Message::RemoveFocus);
+        type_keys(&mut vim, &mut state, "hab");
+
+        assert_eq!(state.text(), "abc");
+    }
+
+    #[test]
+    fn normal_mode_ignores_text() {
+        let mut vim = VimState::default();
+        let mut state = InputState::default();
+
+        type_keys(&mut vim, &mut state, "qwe");
+        vim.handle_message(&mut state, Message::Paste(String::from("foo")));
+
+        assert_eq!(state.text(), "");
+    }
+
+    #[test]
+    fn replace() {
+        let mut vim = VimState::default();
+        let mut state = InputState::default();
+
+        type_keys(&mut vim, &mut state, "ižđš");
+        vim.handle_message(&mut state, Message::RemoveFocus);
+
+        type_keys(&mut vim, &mut state, "hrr");
+        assert_eq!(state.text(), "žrš");
+        assert_eq!(state.cursor_char_idx(), 1);
+        assert_eq!(vim.mode(), VimMode::Normal);
+    }
+
+    #[test]
+    fn delete_line() {
+        let mut vim = VimState::default();
+        let mut state = InputState::default();
+
+        type_keys(&mut vim, &mut state, "ižđš🎈");
+        vim.handle_message(&mut state, Message::RemoveFocus);
+
+        type_keys(&mut vim, &mut state, "dh");
+        assert_eq!(state.text(), "žđš🎈");
+
+        type_keys(&mut vim, &mut state, "dd");
+        assert_eq!(state.text(), "");
+        assert!(state.is_modified());
+    }
+}
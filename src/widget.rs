@@ -1,11 +1,19 @@
-use std::ops::Range;
+use std::{
+    cmp::{max, min},
+    fmt::{self, Debug},
+    ops::Range,
+    rc::Rc,
+};
 
-use crate::InputState;
+use crate::{cell_width, InputState, ViewWindow};
 use ratatui::prelude::*;
 
-/// Input widget
-#[derive(Debug, Clone)]
-pub struct Input {
+/// Callback returning the style of a character given its index and value
+pub type CharStyleFn = Rc<dyn Fn(usize, char) -> Option<Style>>;
+
+/// Colors and styles of the [`Input`] widget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputTheme {
     /// Color of text foreground
     pub text_fg: Color,
     /// Color of text background
@@ -14,88 +22,388 @@ pub struct Input {
     pub cursor_fg: Color,
     /// Color of cursor and selection background
     pub cursor_bg: Color,
-    /// Symbol used to mask the input. Commonly used for passwords
-    pub mask_symbol: Option<char>,
+    /// Style applied on top of the cursor colors while the input is in insert (overwrite) mode
+    pub cursor_style_insert: Style,
+    /// Mark the cursor by applying this style to the character under it instead of inverting the colors,
+    /// so the character stays readable, e.g. an underline as a stand-in for a caret. [`Input::cursor_glyph`] takes precedence
+    pub caret_style: Option<Style>,
+    /// Style of the selection while the input is not in focus, applied on top of the text colors,
+    /// e.g. a gray background. The selection is drawn with the cursor colors when [`None`]
+    pub inactive_selection_style: Option<Style>,
+    /// Style of highlighted ranges, applied on top of the text colors
+    pub highlight_style: Style,
 }
 
-impl Default for Input {
+impl Default for InputTheme {
     fn default() -> Self {
         Self {
             text_fg: Color::White,
             text_bg: Color::Black,
             cursor_fg: Color::Black,
             cursor_bg: Color::White,
+            cursor_style_insert: Style::default(),
+            caret_style: None,
+            inactive_selection_style: None,
+            highlight_style: Style::default().fg(Color::Black).bg(Color::Yellow),
+        }
+    }
+}
+
+/// Input widget
+#[derive(Clone)]
+pub struct Input {
+    /// Colors and styles
+    pub theme: InputTheme,
+    /// Draw the cursor as this glyph (e.g. `▏` or `▁`) in place of the character under it, instead of inverting the colors
+    pub cursor_glyph: Option<char>,
+    /// Glyph (e.g. `▏`) drawn in the first cell while the input is empty and focused,
+    /// so the insertion point isn't mistaken for a space. Takes precedence over [`Input::cursor_glyph`]
+    pub empty_caret: Option<char>,
+    /// Symbol used to mask the input. Commonly used for passwords. Not used while [`InputState::is_revealed`]
+    pub mask_symbol: Option<char>,
+    /// Always draw this many mask symbols no matter how long the value is, so a password doesn't give away its length.
+    /// The cursor is not drawn in this case, as its position would do the same. Only used together with [`Input::mask_symbol`]
+    pub mask_fixed_width: Option<usize>,
+    /// Draw the cursor even when the input is not in focus
+    pub show_cursor_when_unfocused: bool,
+    /// Draw the cursor past the end of the text even when the input is not in focus.
+    /// Has no effect if [`Input::show_cursor_when_unfocused`] already hides the cursor
+    pub show_trailing_cursor_when_unfocused: bool,
+    /// Dim the text outside of the selection while there is one
+    pub dim_unselected: bool,
+    /// Character used to pad the remaining width after the text
+    pub fill_char: char,
+    /// Number of cells a tab is drawn as
+    pub tab_width: usize,
+    /// Style for each visible character. Ranks below the cursor, selection and highlights
+    pub char_style_fn: Option<CharStyleFn>,
+    /// Don't draw the cursor while there is a selection, so the whole selection is styled the same
+    pub hide_cursor_with_selection: bool,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self {
+            theme: InputTheme::default(),
+            cursor_glyph: None,
+            empty_caret: None,
             mask_symbol: None,
+            mask_fixed_width: None,
+            show_cursor_when_unfocused: true,
+            show_trailing_cursor_when_unfocused: true,
+            dim_unselected: false,
+            fill_char: ' ',
+            tab_width: 4,
+            char_style_fn: None,
+            hide_cursor_with_selection: false,
         }
     }
 }
 
+impl Debug for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Input")
+            .field("theme", &self.theme)
+            .field("cursor_glyph", &self.cursor_glyph)
+            .field("empty_caret", &self.empty_caret)
+            .field("mask_symbol", &self.mask_symbol)
+            .field("mask_fixed_width", &self.mask_fixed_width)
+            .field(
+                "show_cursor_when_unfocused",
+                &self.show_cursor_when_unfocused,
+            )
+            .field(
+                "show_trailing_cursor_when_unfocused",
+                &self.show_trailing_cursor_when_unfocused,
+            )
+            .field("dim_unselected", &self.dim_unselected)
+            .field("fill_char", &self.fill_char)
+            .field("tab_width", &self.tab_width)
+            .field("char_style_fn", &self.char_style_fn.as_ref().map(|_| ".."))
+            .field(
+                "hide_cursor_with_selection",
+                &self.hide_cursor_with_selection,
+            )
+            .finish()
+    }
+}
+
 impl StatefulWidget for Input {
     type State = InputState;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
-        let cursor_char_index = state.cursor_char_idx();
-        let view_window = &mut state.view_window;
-        let old_width = view_window.width;
-
-        view_window.width = area.width as usize;
-        if view_window.width > old_width {
-            // Increase view window width to the left, the remaining increase is added to the right
-            let left_increase = if view_window.offsett < view_window.width - old_width {
-                view_window.offsett
-            } else {
-                view_window.width - old_width
-            };
+        self.render_line(area, buf, state, true);
+    }
+}
 
-            view_window.offsett -= left_increase;
-        };
+impl Input {
+    /// Use the given colors and styles
+    pub fn theme(mut self, theme: InputTheme) -> Self {
+        self.theme = theme;
+        self
+    }
 
-        if view_window.width < old_width {
-            // Shrink view window, so that the cursor falls to the very right of the view window,
-            // the remaining width is then shruk from the left
-            let right_shrink = view_window.offsett + old_width - cursor_char_index;
-            let left_shirnk = view_window.width - right_shrink;
+    /// Input masking its value with `•`, for a state made with [`InputState::password`]
+    pub fn password() -> Self {
+        Self {
+            mask_symbol: Some('•'),
+            ..Default::default()
+        }
+    }
 
-            view_window.offsett += left_shirnk;
-            view_window.offsett -= right_shrink;
-        };
+    /// Render into the frame and, while the state is focused and the cursor is in view, put the terminal cursor
+    /// on the input cursor, see [`InputState::screen_cursor_position`]
+    pub fn render_with_cursor(self, frame: &mut Frame, area: Rect, state: &mut InputState) {
+        // A fixed width mask doesn't draw the cursor, as its position would give away the length
+        let fixed_mask = self.mask(state).is_some() && self.mask_fixed_width.is_some();
+        frame.render_stateful_widget(self, area, state);
+
+        if state.is_focused() && !fixed_mask {
+            if let Some((x, y)) = state.screen_cursor_position(area) {
+                frame.set_cursor(x, y);
+            }
+        }
+    }
 
-        let view_window = view_window.clone();
+    /// With `style_runs` the selected cells are styled in contiguous runs at once instead of one by one
+    fn render_line(&self, area: Rect, buf: &mut Buffer, state: &mut InputState, style_runs: bool) {
+        // Only the part of the area inside the buffer can be drawn, a huge area must not size the window
+        let area = area.intersection(buf.area);
+        if area.is_empty() {
+            return;
+        }
 
-        let mut display_text = state
-            .text()
-            .to_string()
-            .chars()
-            .skip(view_window.offsett)
-            .take(view_window.width)
-            .map(|ch| match self.mask_symbol {
-                Some(mask) => mask,
-                None => ch,
-            })
-            .collect::<String>();
+        let cursor_char_index = state.cursor_char_idx();
+        let width = max(area.width as usize, state.min_view_width);
+        state.view_window.resize(width, cursor_char_index);
+        state.fit_tabs(self.cell_tab_width(state));
 
-        for _ in display_text.chars().count()..(view_window.width) {
-            display_text.push(' ');
+        let cells = self.cells(state, &state.view_window, area.width as usize);
+
+        if self.mask(state).is_some() && self.mask_fixed_width.is_some() {
+            for (idx, (_, symbol)) in cells.into_iter().enumerate() {
+                let _ = buf
+                    .get_mut(area.x + idx as u16, area.y)
+                    .set_char(symbol)
+                    .set_fg(self.theme.text_fg)
+                    .set_bg(self.theme.text_bg);
+            }
+
+            return;
         }
 
+        let preedit_len = state.preedit().map_or(0, |preedit| preedit.chars().count());
+
+        let show_cursor = (self.show_cursor_when_unfocused || state.is_focused())
+            && !(self.hide_cursor_with_selection && state.has_selection());
+        let show_cursor = show_cursor
+            && (cursor_char_index < state.text().chars().count()
+                || self.show_trailing_cursor_when_unfocused
+                || state.is_focused());
+
+        let cursor_glyph = match self.empty_caret {
+            Some(caret) if state.text().is_empty() && preedit_len == 0 && state.is_focused() => {
+                Some(caret)
+            }
+            _ => self.cursor_glyph,
+        };
+
+        // Only collect the characters when they are needed for the style callback
+        let value_chars = match self.char_style_fn {
+            Some(_) => state.text().chars().collect::<Vec<_>>(),
+            None => Vec::new(),
+        };
+
         let highlight_range = state
             .selection()
             .map_or(Range::default(), |selection| selection.char_range);
+        let selection_style = match self.theme.inactive_selection_style {
+            Some(style) if !state.is_focused() => Style::default()
+                .fg(self.theme.text_fg)
+                .bg(self.theme.text_bg)
+                .patch(style),
+            _ => Style::default()
+                .fg(self.theme.cursor_fg)
+                .bg(self.theme.cursor_bg),
+        };
+        // Start cell index and length of each run of selected cells
+        let mut selection_runs: Vec<(usize, usize)> = Vec::new();
+
+        for (idx, (position, symbol)) in cells.into_iter().enumerate() {
+            let is_preedit =
+                (cursor_char_index..cursor_char_index + preedit_len).contains(&position);
+
+            // Characters after the preedit are shifted to the right by its length
+            let char_idx = if position < cursor_char_index + preedit_len {
+                position
+            } else {
+                position - preedit_len
+            };
 
-        for (idx, symbol) in display_text.chars().enumerate() {
             let cell = buf
                 .get_mut(area.x + idx as u16, area.y)
                 .set_symbol(symbol.to_string().as_str());
 
-            let _ = if highlight_range.contains(&(view_window.offsett + idx))
-                || state.cursor_char_idx() == view_window.offsett + idx
+            let cell = if is_preedit {
+                cell.set_fg(self.theme.text_fg)
+                    .set_bg(self.theme.text_bg)
+                    .set_style(Style::default().add_modifier(Modifier::UNDERLINED))
+            } else if show_cursor && cursor_char_index == char_idx {
+                // Without inverting, the cell keeps the colors it would have otherwise
+                let (fg, bg) = if highlight_range.contains(&char_idx) {
+                    (self.theme.cursor_fg, self.theme.cursor_bg)
+                } else {
+                    (self.theme.text_fg, self.theme.text_bg)
+                };
+
+                let cell = match (cursor_glyph, self.theme.caret_style) {
+                    (Some(glyph), _) => cell.set_char(glyph).set_fg(fg).set_bg(bg),
+                    (None, Some(caret_style)) => cell.set_fg(fg).set_bg(bg).set_style(caret_style),
+                    (None, None) => cell
+                        .set_fg(self.theme.cursor_fg)
+                        .set_bg(self.theme.cursor_bg),
+                };
+                if state.insert_mode() {
+                    cell.set_style(self.theme.cursor_style_insert)
+                } else {
+                    cell
+                }
+            } else if highlight_range.contains(&char_idx) {
+                if style_runs {
+                    match selection_runs.last_mut() {
+                        Some((start, len)) if *start + *len == idx => *len += 1,
+                        _ => selection_runs.push((idx, 1)),
+                    }
+                    cell
+                } else {
+                    cell.set_style(selection_style)
+                }
+            } else if state
+                .highlights()
+                .iter()
+                .any(|highlight| highlight.contains(&char_idx))
             {
-                cell.set_fg(self.cursor_fg).set_bg(self.cursor_bg)
+                cell.set_fg(self.theme.text_fg)
+                    .set_bg(self.theme.text_bg)
+                    .set_style(self.theme.highlight_style)
+            } else if let Some(style) = self.char_style_fn.as_ref().and_then(|char_style_fn| {
+                value_chars
+                    .get(char_idx)
+                    .and_then(|c| char_style_fn(char_idx, *c))
+            }) {
+                cell.set_fg(self.theme.text_fg)
+                    .set_bg(self.theme.text_bg)
+                    .set_style(style)
             } else {
-                cell.set_fg(self.text_fg).set_bg(self.text_bg)
+                cell.set_fg(self.theme.text_fg).set_bg(self.theme.text_bg)
             };
+
+            if self.dim_unselected
+                && !highlight_range.is_empty()
+                && !highlight_range.contains(&char_idx)
+            {
+                let _ = cell.set_style(Style::default().add_modifier(Modifier::DIM));
+            }
+        }
+
+        for (start, len) in selection_runs {
+            buf.set_style(
+                Rect::new(area.x + start as u16, area.y, len as u16, 1),
+                selection_style,
+            );
         }
     }
+
+    /// Text the widget draws for the state in the given width, masked, scrolled and padded like in [`StatefulWidget::render`].
+    /// The view window of the state is left as it is
+    pub fn visible_text(&self, state: &InputState, width: usize) -> String {
+        let mut view_window = state.view_window.clone();
+        view_window.resize(max(width, state.min_view_width), state.cursor_char_idx());
+
+        self.cells(state, &view_window, width)
+            .into_iter()
+            .map(|(_, symbol)| symbol)
+            .collect()
+    }
+
+    /// Columns needed to show the whole value and the cursor after it without scrolling, e.g. for sizing the area
+    /// with [`Constraint::Length`]. A mask of fixed width needs just as many columns, no matter the value
+    pub fn desired_width(&self, state: &InputState) -> u16 {
+        let width = match (self.mask(state), self.mask_fixed_width) {
+            (Some(_), Some(mask_fixed_width)) => mask_fixed_width,
+            _ => {
+                let text = [state.text(), state.preedit().unwrap_or_default()].concat();
+                let tab_width = self.cell_tab_width(state);
+                let text_width: usize = text.chars().map(|ch| cell_width(ch, tab_width)).sum();
+                text_width + 1
+            }
+        };
+
+        min(width, u16::MAX as usize) as u16
+    }
+
+    /// Cells a tab takes up, masked it is drawn as a single mask symbol like any other character
+    fn cell_tab_width(&self, state: &InputState) -> usize {
+        match self.mask(state) {
+            Some(_) => 1,
+            None => self.tab_width,
+        }
+    }
+
+    /// Symbol masking the value, unless the state reveals it
+    fn mask(&self, state: &InputState) -> Option<char> {
+        self.mask_symbol.filter(|_| !state.is_revealed())
+    }
+
+    /// Symbols of the first `width` cells of the view window,
+    /// each with the position of the character it belongs to counting the preedit
+    fn cells(
+        &self,
+        state: &InputState,
+        view_window: &ViewWindow,
+        width: usize,
+    ) -> Vec<(usize, char)> {
+        // The window can be wider than the area, cells past the area would be clipped anyway so they are not built
+        let cells_width = min(view_window.width, width);
+
+        if let (Some(mask), Some(mask_fixed_width)) = (self.mask(state), self.mask_fixed_width) {
+            return (0..cells_width)
+                .map(|idx| {
+                    let symbol = if idx < mask_fixed_width {
+                        mask
+                    } else {
+                        self.fill_char
+                    };
+                    (idx, symbol)
+                })
+                .collect();
+        }
+
+        let cursor_byte_idx = state.cursor_byte_idx();
+        let display_chars = [
+            &state.text()[..cursor_byte_idx],
+            state.preedit().unwrap_or_default(),
+            &state.text()[cursor_byte_idx..],
+        ]
+        .concat()
+        .chars()
+        .map(|ch| match self.mask(state) {
+            Some(mask) => mask,
+            None => ch,
+        })
+        .collect::<Vec<_>>();
+
+        // Every cell remembers the position of the character it belongs to
+        state
+            .cell_positions(view_window, width, self.cell_tab_width(state))
+            .into_iter()
+            .map(|position| match display_chars.get(position) {
+                Some('\t') => (position, ' '),
+                Some(ch) => (position, *ch),
+                None => (position, self.fill_char),
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -105,7 +413,7 @@ mod tests {
     use ratatui::assert_buffer_eq;
 
     use super::*;
-    use crate::{Message, ViewWindow};
+    use crate::{Hit, Message, ViewWindow};
 
     fn new_buffer(
         content: &str,
@@ -152,6 +460,60 @@ mod tests {
         )
     }
 
+    #[test]
+    fn area_larger_than_buffer() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 5, 1));
+        let widget = Input::default();
+        let mut state = InputState::default();
+        state.set_text("foo");
+
+        widget
+            .clone()
+            .render(Rect::new(0, 0, u16::MAX, u16::MAX), &mut buf, &mut state);
+
+        assert_eq!(state.view_window.width, 5);
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "foo  ",
+                None,
+                3,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+
+        // An area completely outside of the buffer draws nothing
+        widget
+            .clone()
+            .render(Rect::new(10, 10, 5, 1), &mut buf, &mut state);
+
+        assert_eq!(state.view_window.width, 5);
+    }
+
+    #[test]
+    fn custom_theme() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 5, 1));
+        let widget = Input::default().theme(InputTheme {
+            text_fg: Color::Green,
+            text_bg: Color::Blue,
+            cursor_fg: Color::Blue,
+            cursor_bg: Color::Green,
+            ..Default::default()
+        });
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("foo")));
+        state.handle_message(Message::MoveLeft);
+        widget.render(buf.area, &mut buf, &mut state);
+
+        assert_buffer_eq!(
+            buf,
+            new_buffer("foo  ", None, 2, buf.area, Color::Blue, Color::Green)
+        );
+    }
+
     #[test]
     fn cursor_highlight() {
         let mut buf = Buffer::empty(Rect::new(0, 0, 5, 1));
@@ -162,7 +524,14 @@ mod tests {
 
         assert_buffer_eq!(
             buf,
-            new_buffer("     ", None, 0, buf.area, widget.text_bg, widget.text_fg)
+            new_buffer(
+                "     ",
+                None,
+                0,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
         )
     }
 
@@ -186,7 +555,14 @@ mod tests {
         );
         assert_buffer_eq!(
             buf,
-            new_buffer(" bar ", None, 4, buf.area, widget.text_bg, widget.text_fg)
+            new_buffer(
+                " bar ",
+                None,
+                4,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
         );
     }
 
@@ -217,7 +593,939 @@ mod tests {
         );
         assert_buffer_eq!(
             buf,
-            new_buffer(" bar ", None, 4, buf.area, widget.text_bg, widget.text_fg)
+            new_buffer(
+                " bar ",
+                None,
+                4,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
         )
     }
+
+    #[test]
+    fn highlighted_ranges() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 12, 1));
+        let widget = Input::default();
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("foo bar baz")));
+        let highlights = state.find_all("ba", false);
+        state.set_highlights(highlights);
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        let mut expected = new_buffer(
+            "foo bar baz ",
+            None,
+            11,
+            buf.area,
+            widget.theme.text_bg,
+            widget.theme.text_fg,
+        );
+        for idx in [4, 5, 8, 9] {
+            let _ = expected
+                .get_mut(idx, 0)
+                .set_style(widget.theme.highlight_style);
+        }
+
+        assert_eq!(state.highlights(), &[4..6, 8..10]);
+        assert_buffer_eq!(buf, expected);
+    }
+
+    #[test]
+    fn preedit_is_underlined_at_cursor() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 6, 1));
+        let widget = Input::default();
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("ab")));
+        state.handle_message(Message::MoveLeft);
+        state.set_preedit(Some(String::from("にほ")));
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        let mut expected = new_buffer(
+            "aにほb  ",
+            None,
+            3,
+            buf.area,
+            widget.theme.text_bg,
+            widget.theme.text_fg,
+        );
+        for idx in [1, 2] {
+            let _ = expected
+                .get_mut(idx, 0)
+                .set_style(Style::default().add_modifier(Modifier::UNDERLINED));
+        }
+
+        assert_eq!(state.text(), "ab");
+        assert_buffer_eq!(buf, expected);
+    }
+
+    #[test]
+    fn cursor_hidden_when_unfocused() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 5, 1));
+        let widget = Input {
+            show_cursor_when_unfocused: false,
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "     ",
+                None,
+                usize::MAX,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+
+        state.handle_message(Message::Focus);
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "     ",
+                None,
+                0,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+    }
+
+    #[test]
+    fn trailing_cursor_hidden_when_unfocused() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 5, 1));
+        let widget = Input {
+            show_trailing_cursor_when_unfocused: false,
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("foo")));
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "foo  ",
+                None,
+                usize::MAX,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+
+        // Away from the end the cursor is still shown
+        state.handle_message(Message::MoveLeft);
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "foo  ",
+                None,
+                2,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+    }
+
+    #[test]
+    fn trailing_cursor_shown_when_focused() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 5, 1));
+        let widget = Input {
+            show_trailing_cursor_when_unfocused: false,
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Focus);
+        state.handle_message(Message::Paste(String::from("foo")));
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "foo  ",
+                None,
+                3,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+    }
+
+    #[test]
+    fn cursor_shown_when_unfocused() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 5, 1));
+        let widget = Input {
+            show_cursor_when_unfocused: true,
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "     ",
+                None,
+                0,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+
+        state.handle_message(Message::Focus);
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "     ",
+                None,
+                0,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+    }
+
+    #[test]
+    fn dim_unselected() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 8, 1));
+        let widget = Input {
+            dim_unselected: true,
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("foo bar")));
+        state.handle_message(Message::MoveLeft);
+        state.handle_message(Message::MoveLeftWithSelection);
+        state.handle_message(Message::MoveLeftWithSelection);
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        let mut expected = new_buffer(
+            "foo bar ",
+            Some(4..7),
+            4,
+            buf.area,
+            widget.theme.text_bg,
+            widget.theme.text_fg,
+        );
+        for idx in [0, 1, 2, 3, 7] {
+            let _ = expected
+                .get_mut(idx, 0)
+                .set_style(Style::default().add_modifier(Modifier::DIM));
+        }
+
+        assert_eq!(&*state.selection().unwrap(), "bar");
+        assert_buffer_eq!(buf, expected);
+    }
+
+    #[test]
+    fn dim_unselected_without_selection() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 8, 1));
+        let widget = Input {
+            dim_unselected: true,
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("foo bar")));
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "foo bar ",
+                None,
+                7,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+    }
+
+    #[test]
+    fn hide_cursor_with_selection() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 8, 1));
+        let widget = Input {
+            hide_cursor_with_selection: true,
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("foo bar")));
+        state.handle_message(Message::JumpToStart);
+        state.handle_message(Message::MoveRightWithSelection);
+        state.handle_message(Message::MoveRightWithSelection);
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "foo bar ",
+                Some(0..3),
+                2,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+    }
+
+    #[test]
+    fn cursor_style_insert() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 4, 1));
+        let widget = Input::default().theme(InputTheme {
+            cursor_style_insert: Style::default().fg(Color::Red).bg(Color::Blue),
+            ..Default::default()
+        });
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("foo")));
+        state.handle_message(Message::MoveLeft);
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "foo ",
+                None,
+                2,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+
+        state.handle_message(Message::ToggleInsertMode);
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        let mut expected = new_buffer(
+            "foo ",
+            None,
+            4,
+            buf.area,
+            widget.theme.text_bg,
+            widget.theme.text_fg,
+        );
+        let _ = expected
+            .get_mut(2, 0)
+            .set_fg(Color::Red)
+            .set_bg(Color::Blue);
+        assert_buffer_eq!(buf, expected);
+    }
+
+    #[test]
+    fn caret_style() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 4, 1));
+        let caret_style = Style::default().add_modifier(Modifier::UNDERLINED);
+        let widget = Input::default().theme(InputTheme {
+            caret_style: Some(caret_style),
+            ..Default::default()
+        });
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("žđš")));
+        state.handle_message(Message::MoveLeft);
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        let mut expected = new_buffer(
+            "žđš ",
+            None,
+            usize::MAX,
+            buf.area,
+            widget.theme.text_bg,
+            widget.theme.text_fg,
+        );
+        let _ = expected.get_mut(2, 0).set_style(caret_style);
+        assert_buffer_eq!(buf, expected);
+    }
+
+    #[test]
+    fn cursor_glyph_beam() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 5, 1));
+        let widget = Input {
+            cursor_glyph: Some('▏'),
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("foo")));
+        state.handle_message(Message::MoveLeft);
+        state.handle_message(Message::MoveLeft);
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "f▏o  ",
+                None,
+                usize::MAX,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+    }
+
+    #[test]
+    fn cursor_glyph_block_at_end() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 5, 1));
+        let widget = Input {
+            cursor_glyph: Some('█'),
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("foo")));
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "foo█ ",
+                None,
+                usize::MAX,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+    }
+
+    #[test]
+    fn empty_caret() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 5, 1));
+        let widget = Input {
+            empty_caret: Some('▏'),
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Focus);
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "▏    ",
+                None,
+                usize::MAX,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+
+        // Unfocused, the regular cursor is drawn
+        state.handle_message(Message::RemoveFocus);
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "     ",
+                None,
+                0,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+
+        // As soon as there is text, so is the regular cursor
+        state.handle_message(Message::Focus);
+        state.handle_message(Message::Char('a'));
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "a    ",
+                None,
+                1,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+    }
+
+    #[test]
+    fn inactive_selection_style() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 5, 1));
+        let inactive_style = Style::default().bg(Color::DarkGray);
+        let widget = Input::default().theme(InputTheme {
+            inactive_selection_style: Some(inactive_style),
+            ..Default::default()
+        });
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Focus);
+        state.handle_message(Message::Paste(String::from("foo")));
+        state.handle_message(Message::JumpToStartWithSelection);
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "foo  ",
+                Some(0..3),
+                0,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+
+        // Unfocused, the selection is kept but muted, the cursor is drawn as usual
+        state.handle_message(Message::RemoveFocus);
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        let mut expected = new_buffer(
+            "foo  ",
+            None,
+            0,
+            buf.area,
+            widget.theme.text_bg,
+            widget.theme.text_fg,
+        );
+        for x in 1..3 {
+            let _ = expected.get_mut(x, 0).set_style(inactive_style);
+        }
+        assert_buffer_eq!(buf, expected);
+    }
+
+    #[test]
+    fn selection_style_runs() {
+        let area = Rect::new(0, 0, 300, 1);
+        let widget = Input {
+            dim_unselected: true,
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste("žđš🎈 ".repeat(100)));
+        state.handle_message(Message::Focus);
+        state.handle_message(Message::SelectToIndex(20));
+        assert!(state.has_selection());
+
+        let mut buf = Buffer::empty(area);
+        let mut runs_buf = Buffer::empty(area);
+        widget.render_line(area, &mut buf, &mut state, false);
+        widget.render_line(area, &mut runs_buf, &mut state, true);
+        assert_buffer_eq!(runs_buf, buf);
+
+        // Unfocused with a muted selection
+        state.handle_message(Message::RemoveFocus);
+        assert!(state.has_selection());
+        let theme = InputTheme {
+            inactive_selection_style: Some(Style::default().bg(Color::DarkGray)),
+            ..widget.theme
+        };
+        let widget = widget.theme(theme);
+        widget.render_line(area, &mut buf, &mut state, false);
+        widget.render_line(area, &mut runs_buf, &mut state, true);
+        assert_buffer_eq!(runs_buf, buf);
+    }
+
+    fn buffer_text(buf: &Buffer) -> String {
+        buf.content.iter().map(|cell| cell.symbol()).collect()
+    }
+
+    #[test]
+    fn visible_text_scrolled() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 5, 1));
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("žđš🎈 bar")));
+        state.handle_message(Message::JumpToStart);
+        state.handle_message(Message::JumpToEnd);
+        let visible_text = state.visible_text(5);
+
+        Input::default().render(buf.area, &mut buf, &mut state);
+        assert_eq!(visible_text, buffer_text(&buf));
+        assert_eq!(visible_text, " bar ");
+    }
+
+    #[test]
+    fn visible_text_masked() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 6, 1));
+        let widget = Input {
+            mask_symbol: Some('•'),
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("pass")));
+        let visible_text = widget.visible_text(&state, 6);
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        assert_eq!(visible_text, buffer_text(&buf));
+        assert_eq!(visible_text, "••••  ");
+
+        let widget = Input {
+            mask_fixed_width: Some(3),
+            ..widget
+        };
+        assert_eq!(widget.visible_text(&state, 6), "•••   ");
+    }
+
+    #[test]
+    fn visible_text_zero_width() {
+        let mut state = InputState::default();
+        assert_eq!(state.visible_text(0), "");
+
+        state.set_text("foo");
+        assert_eq!(state.visible_text(0), "");
+    }
+
+    #[test]
+    fn visible_text_narrower_than_render() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 10, 1));
+        let mut state = InputState::default();
+        state.set_text("foo bar baz");
+        state.handle_message(Message::JumpToStart);
+        Input::default().render(buf.area, &mut buf, &mut state);
+
+        assert_eq!(state.visible_text(5), "foo b");
+
+        state.handle_message(Message::JumpToEnd);
+        Input::default().render(buf.area, &mut buf, &mut state);
+        assert_eq!(state.visible_text(5), " baz ");
+    }
+
+    #[test]
+    fn password() {
+        let mut state = InputState::password();
+        state.set_text("hunter2");
+
+        assert_eq!(Input::password().visible_text(&state, 8), "••••••• ");
+    }
+
+    #[test]
+    fn desired_width() {
+        let mut state = InputState::default();
+        assert_eq!(Input::default().desired_width(&state), 1);
+
+        state.set_text("žđš");
+        assert_eq!(Input::default().desired_width(&state), 4);
+
+        state.set_text("žđš\tfoo bar baz");
+        assert_eq!(Input::default().desired_width(&state), 19);
+        assert_eq!(Input::password().desired_width(&state), 16);
+
+        let widget = Input {
+            mask_fixed_width: Some(8),
+            ..Input::password()
+        };
+        assert_eq!(widget.desired_width(&state), 8);
+
+        state.set_text("ž".repeat(70_000));
+        assert_eq!(Input::default().desired_width(&state), u16::MAX);
+    }
+
+    #[test]
+    fn render_with_cursor() {
+        let mut terminal = Terminal::new(backend::TestBackend::new(10, 3)).unwrap();
+        let area = Rect::new(2, 1, 6, 1);
+        let mut state = InputState::default();
+        state.set_text("žđš foo bar");
+        state.handle_message(Message::JumpToStart);
+        state.handle_message(Message::MoveRight);
+
+        // Not focused, so the cursor is left where it was
+        let _ = terminal
+            .draw(|frame| Input::default().render_with_cursor(frame, area, &mut state))
+            .unwrap();
+        assert_eq!(terminal.get_cursor().unwrap(), (0, 0));
+
+        state.handle_message(Message::Focus);
+        let _ = terminal
+            .draw(|frame| Input::default().render_with_cursor(frame, area, &mut state))
+            .unwrap();
+        assert_eq!(terminal.get_cursor().unwrap(), (3, 1));
+
+        // Scrolled along with the view window
+        state.handle_message(Message::JumpToEnd);
+        let _ = terminal
+            .draw(|frame| Input::default().render_with_cursor(frame, area, &mut state))
+            .unwrap();
+        assert_eq!(terminal.get_cursor().unwrap(), (7, 1));
+    }
+
+    #[test]
+    fn render_with_cursor_after_tab() {
+        let mut terminal = Terminal::new(backend::TestBackend::new(10, 3)).unwrap();
+        let area = Rect::new(2, 1, 8, 1);
+        let mut state = InputState::default();
+        state.set_text("a\tbc");
+        state.handle_message(Message::Focus);
+        state.handle_message(Message::MoveLeft);
+
+        // On the `c` of `a    bc`
+        let _ = terminal
+            .draw(|frame| Input::default().render_with_cursor(frame, area, &mut state))
+            .unwrap();
+        assert_eq!(terminal.get_cursor().unwrap(), (8, 1));
+
+        // The tab would push the cursor out of view, so the window scrolls to `bc`
+        let area = Rect::new(2, 1, 4, 1);
+        let mut state = InputState::default();
+        state.set_text("a\tbc");
+        state.handle_message(Message::Focus);
+        let _ = terminal
+            .draw(|frame| Input::default().render_with_cursor(frame, area, &mut state))
+            .unwrap();
+        assert_eq!(terminal.get_cursor().unwrap(), (4, 1));
+    }
+
+    #[test]
+    fn render_with_cursor_after_preedit() {
+        let mut terminal = Terminal::new(backend::TestBackend::new(10, 3)).unwrap();
+        let mut state = InputState::default();
+        state.set_text("žđ");
+        state.handle_message(Message::Focus);
+        state.set_preedit(Some(String::from("xyz")));
+
+        // The cursor stays behind the preedit of `žđxyz`
+        let area = Rect::new(2, 1, 8, 1);
+        let _ = terminal
+            .draw(|frame| Input::default().render_with_cursor(frame, area, &mut state))
+            .unwrap();
+        assert_eq!(terminal.get_cursor().unwrap(), (7, 1));
+
+        // The window scrolls for the preedit to fit, showing `xyz`
+        let area = Rect::new(2, 1, 4, 1);
+        let mut state = InputState::default();
+        state.set_text("žđ");
+        state.handle_message(Message::Focus);
+        state.set_preedit(Some(String::from("xyz")));
+        let _ = terminal
+            .draw(|frame| Input::default().render_with_cursor(frame, area, &mut state))
+            .unwrap();
+        assert_eq!(terminal.get_cursor().unwrap(), (5, 1));
+    }
+
+    #[test]
+    fn toggle_reveal() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 6, 1));
+        let widget = Input {
+            mask_fixed_width: Some(5),
+            ..Input::password()
+        };
+        let mut state = InputState::password();
+        state.set_text("žđš");
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        assert_eq!(buffer_text(&buf), "••••• ");
+
+        state.handle_message(Message::ToggleReveal);
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        assert_eq!(buffer_text(&buf), "žđš   ");
+
+        state.handle_message(Message::ToggleReveal);
+        widget.render(buf.area, &mut buf, &mut state);
+        assert_eq!(buffer_text(&buf), "••••• ");
+    }
+
+    #[test]
+    fn mask_fixed_width() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 10, 1));
+        let widget = Input {
+            mask_symbol: Some('•'),
+            mask_fixed_width: Some(8),
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("žđš🎈")));
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        assert_eq!(state.text(), "žđš🎈");
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "••••••••  ",
+                None,
+                usize::MAX,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+    }
+
+    #[test]
+    fn tab_width() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 8, 1));
+        let widget = Input {
+            tab_width: 4,
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("a\tb")));
+        state.handle_message(Message::MoveLeft);
+        state.handle_message(Message::MoveLeft);
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        // The cursor is on the tab, so all of its cells are highlighted
+        let mut expected = new_buffer(
+            "a    b  ",
+            Some(1..5),
+            usize::MAX,
+            buf.area,
+            widget.theme.text_bg,
+            widget.theme.text_fg,
+        );
+        assert_buffer_eq!(buf, expected);
+
+        // Behind the text the cursor is in the first free cell
+        state.handle_message(Message::JumpToEnd);
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        expected = new_buffer(
+            "a    b  ",
+            None,
+            6,
+            buf.area,
+            widget.theme.text_bg,
+            widget.theme.text_fg,
+        );
+        assert_buffer_eq!(buf, expected);
+    }
+
+    #[test]
+    fn tab_width_scrolls_cursor_into_view() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 4, 1));
+        let widget = Input::default();
+        let mut state = InputState::default();
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+        state.handle_message(Message::Paste(String::from("a\tb")));
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "b   ",
+                None,
+                1,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+
+        // The state maps columns the same way
+        assert_eq!(state.view_window.offsett, 2);
+        assert_eq!(state.hit_test(0), Hit::Char(2));
+        assert_eq!(state.hit_test(1), Hit::Cursor);
+        assert_eq!(state.screen_cursor_position(buf.area), Some((1, 0)));
+    }
+
+    #[test]
+    fn min_view_width() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 2, 1));
+        let widget = Input::default();
+        let mut state = InputState::default();
+        state.set_min_view_width(5);
+
+        state.handle_message(Message::Paste(String::from("žđš")));
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        assert_eq!(state.view_window.width, 5);
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "žđ",
+                None,
+                usize::MAX,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+    }
+
+    #[test]
+    fn fill_char() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 6, 1));
+        let widget = Input {
+            fill_char: '_',
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("ž🎈")));
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        assert_buffer_eq!(
+            buf,
+            new_buffer(
+                "ž🎈____",
+                None,
+                2,
+                buf.area,
+                widget.theme.text_bg,
+                widget.theme.text_fg
+            )
+        );
+    }
+
+    #[test]
+    fn char_style_fn() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 5, 1));
+        let digit_style = Style::default().fg(Color::Red);
+        let widget = Input {
+            char_style_fn: Some(Rc::new(move |_, c| {
+                if c.is_ascii_digit() {
+                    Some(digit_style)
+                } else {
+                    None
+                }
+            })),
+            ..Default::default()
+        };
+        let mut state = InputState::default();
+
+        state.handle_message(Message::Paste(String::from("a1b2")));
+
+        widget.clone().render(buf.area, &mut buf, &mut state);
+
+        let mut expected = new_buffer(
+            "a1b2 ",
+            None,
+            4,
+            buf.area,
+            widget.theme.text_bg,
+            widget.theme.text_fg,
+        );
+        for idx in [1, 3] {
+            let _ = expected.get_mut(idx, 0).set_style(digit_style);
+        }
+
+        assert_buffer_eq!(buf, expected);
+    }
 }
@@ -0,0 +1,19 @@
+/// Decides which characters make up a word, used by word based operations of the [`crate::InputState`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WordBoundary {
+    /// Words consist of alphanumeric characters and underscores, everything else separates them
+    #[default]
+    AlphaNumeric,
+    /// Words are separated only by whitespace
+    Whitespace,
+}
+
+impl WordBoundary {
+    /// Whether the character is part of a word
+    pub fn is_word_char(&self, c: char) -> bool {
+        match self {
+            WordBoundary::AlphaNumeric => c.is_alphanumeric() || c == '_',
+            WordBoundary::Whitespace => !c.is_whitespace(),
+        }
+    }
+}